@@ -0,0 +1,60 @@
+//! Latency of fetching IDs from a `snowflake-uds` style daemon compared with
+//! calling `next_id` in-process.
+//!
+//! Run with `cargo bench --bench uds`.
+
+#[cfg(unix)]
+fn main() {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use id_gnrt_rust_impl::Snowflake;
+    use id_gnrt_rust_impl::server::uds::{UdsClient, UdsServer};
+
+    const ITERATIONS: usize = 100_000;
+    const BATCH: u32 = 100;
+
+    /// Time `op` `ITERATIONS` times and print the mean, median and p99
+    fn report(name: &str, per_call: u32, mut op: impl FnMut()) {
+        let mut samples = Vec::with_capacity(ITERATIONS);
+        for _ in 0..ITERATIONS {
+            let start = Instant::now();
+            op();
+            samples.push(start.elapsed());
+        }
+        samples.sort();
+        let total: Duration = samples.iter().sum();
+        let mean = total / ITERATIONS as u32;
+        println!(
+            "{:<24} mean {:>9.2?}  p50 {:>9.2?}  p99 {:>9.2?}  per id {:>9.2?}",
+            name,
+            mean,
+            samples[ITERATIONS / 2],
+            samples[ITERATIONS * 99 / 100],
+            mean / per_call
+        );
+    }
+
+    let generator = Snowflake::new(1, 1);
+    report("in-process next_id", 1, || {
+        std::hint::black_box(generator.next_id().unwrap());
+    });
+
+    let path = std::env::temp_dir().join(format!("snowflake-bench-{}.sock", std::process::id()));
+    let server = UdsServer::bind(&path, Arc::new(Snowflake::new(1, 2))).unwrap();
+    thread::spawn(move || server.serve());
+    let mut client = UdsClient::connect(&path).unwrap();
+    report("uds next_id", 1, || {
+        std::hint::black_box(client.next_id().unwrap());
+    });
+    report("uds next_ids(100)", BATCH, || {
+        std::hint::black_box(client.next_ids(BATCH).unwrap());
+    });
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("the uds benchmark needs Unix domain sockets");
+}
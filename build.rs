@@ -0,0 +1,19 @@
+//! Compiles `proto/snowflake.proto` when the `grpc` feature is enabled.
+//!
+//! The descriptors are produced by `protox`, so no `protoc` install is needed.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    if let Err(err) = compile_protos() {
+        panic!("failed to compile protos: {}", err);
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/snowflake.proto");
+    let descriptors = protox::compile(["snowflake.proto"], ["proto"])?;
+    tonic_prost_build::configure().compile_fds(descriptors)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Async ID generation that sleeps instead of spinning.
+//!
+//! Available with the `async` feature. Waiting is done with
+//! [`tokio::time::sleep`], so these methods need a Tokio runtime with the
+//! time driver enabled.
+
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::error::SnowflakeError;
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+
+impl<C: Clock> Snowflake<C> {
+    /// Generate the next unique ID, sleeping while the clock catches up
+    ///
+    /// IDs are claimed through the same atomic state as
+    /// [`next_id`](Snowflake::next_id), so sync and async callers can share
+    /// one generator.
+    pub async fn next_id_async(&self) -> Result<SnowflakeId, SnowflakeError> {
+        loop {
+            match self.try_next_id() {
+                Err(SnowflakeError::WouldBlock { until }) => {
+                    let behind = until.saturating_sub(self.clock().now_millis()) + 1;
+                    tokio::time::sleep(Duration::from_millis(behind)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::clock::ManualClock;
+    use crate::epoch::Epoch;
+    use crate::generator::Snowflake;
+    use crate::layout::BitLayout;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_no_duplicates_across_tasks() {
+        const TASKS: usize = 8;
+        const PER_TASK: usize = 5_000;
+
+        let generator = Arc::new(Snowflake::new(1, 1));
+        let handles: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                tokio::spawn(async move {
+                    let mut ids = Vec::with_capacity(PER_TASK);
+                    for _ in 0..PER_TASK {
+                        ids.push(generator.next_id_async().await.unwrap());
+                    }
+                    ids
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::with_capacity(TASKS * PER_TASK);
+        for handle in handles {
+            for id in handle.await.unwrap() {
+                assert!(seen.insert(id), "duplicate id {}", id);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sleeps_through_sequence_exhaustion() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = Snowflake::builder()
+            .layout(BitLayout::new(61, 0, 0, 2).unwrap())
+            .epoch(Epoch::UNIX)
+            .clock(Arc::clone(&clock))
+            .build()
+            .unwrap();
+        for _ in 0..4 {
+            generator.next_id_async().await.unwrap();
+        }
+
+        let advancer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            clock.set(1001);
+        });
+        assert_eq!(generator.next_id_async().await.unwrap().as_u64(), 4004);
+        advancer.await.unwrap();
+    }
+}
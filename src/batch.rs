@@ -0,0 +1,74 @@
+//! Batches of IDs reserved in one go.
+
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+use crate::id::SnowflakeId;
+
+/// IDs reserved by [`Snowflake::reserve`](crate::Snowflake::reserve)
+///
+/// The IDs are claimed as a few contiguous runs, one per millisecond the
+/// batch spans, and are yielded in increasing order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdBatch {
+    ranges: Vec<Range<u64>>,
+    remaining: usize,
+}
+
+impl IdBatch {
+    pub(crate) fn new(ranges: Vec<Range<u64>>) -> Self {
+        let remaining = ranges
+            .iter()
+            .map(|range| (range.end - range.start) as usize)
+            .sum();
+        IdBatch {
+            ranges: ranges.into_iter().rev().collect(),
+            remaining,
+        }
+    }
+
+    /// The contiguous runs of IDs not yet yielded, in increasing order
+    pub fn ranges(&self) -> impl Iterator<Item = &Range<u64>> {
+        self.ranges.iter().rev()
+    }
+}
+
+impl Iterator for IdBatch {
+    type Item = SnowflakeId;
+
+    fn next(&mut self) -> Option<SnowflakeId> {
+        loop {
+            let range = self.ranges.last_mut()?;
+            if let Some(id) = range.next() {
+                self.remaining -= 1;
+                return Some(SnowflakeId::from_u64(id));
+            }
+            self.ranges.pop();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for IdBatch {}
+
+impl FusedIterator for IdBatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yields_ranges_in_order() {
+        let mut batch = IdBatch::new(vec![10..12, 20..21, 30..30]);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch.next(), Some(SnowflakeId::from_u64(10)));
+        assert_eq!(
+            batch.ranges().cloned().collect::<Vec<_>>(),
+            [11..12, 20..21, 30..30]
+        );
+        assert_eq!(batch.map(SnowflakeId::as_u64).collect::<Vec<_>>(), [11, 20]);
+    }
+}
@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::process::ExitCode;
+
+use id_gnrt_rust_impl::server::grpc::GrpcServer;
+use id_gnrt_rust_impl::server::run_binary;
+
+const USAGE: &str = "\
+Usage: snowflake-grpc [options]
+
+Serves IDs over gRPC: the NextId, NextIds and Decode RPCs of
+snowflake.v1.SnowflakeService, defined in proto/snowflake.proto.
+
+Options:
+  --listen ADDR       Address to listen on [default: 127.0.0.1:50051]
+  --datacenter N      Datacenter id [default: 0]
+  --machine N         Machine id [default: 0]
+  --epoch EPOCH       RFC 3339 time or Unix milliseconds [default: 2010-11-04T01:42:54.657Z]
+  --layout T,D,M,S    Bit widths of timestamp, datacenter, machine and sequence
+                      [default: 41,5,5,12]";
+
+fn main() -> ExitCode {
+    run_binary(
+        "snowflake-grpc",
+        USAGE,
+        std::env::args().skip(1).collect(),
+        ([127, 0, 0, 1], 50051).into(),
+        |options, generator| {
+            tokio::runtime::Runtime::new()?.block_on(async {
+                let server = GrpcServer::bind(options.listen, generator).await?;
+                eprintln!("listening on http://{}", server.local_addr()?);
+                server.serve().await?;
+                Ok::<(), Box<dyn Error>>(())
+            })
+        },
+    )
+}
@@ -0,0 +1,32 @@
+use std::process::ExitCode;
+
+use id_gnrt_rust_impl::server::http::HttpServer;
+use id_gnrt_rust_impl::server::run_binary;
+
+const USAGE: &str = "\
+Usage: snowflake-http [options]
+
+Serves IDs over HTTP: GET /id, /ids?count=N, /decode/{id} and /health, and
+GET /metrics when built with the metrics feature.
+
+Options:
+  --listen ADDR       Address to listen on [default: 127.0.0.1:8080]
+  --datacenter N      Datacenter id [default: 0]
+  --machine N         Machine id [default: 0]
+  --epoch EPOCH       RFC 3339 time or Unix milliseconds [default: 2010-11-04T01:42:54.657Z]
+  --layout T,D,M,S    Bit widths of timestamp, datacenter, machine and sequence
+                      [default: 41,5,5,12]";
+
+fn main() -> ExitCode {
+    run_binary(
+        "snowflake-http",
+        USAGE,
+        std::env::args().skip(1).collect(),
+        ([127, 0, 0, 1], 8080).into(),
+        |options, generator| {
+            let server = HttpServer::bind(options.listen, generator)?;
+            eprintln!("listening on http://{}", server.local_addr()?);
+            Ok(server.serve()?)
+        },
+    )
+}
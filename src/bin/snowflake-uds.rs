@@ -0,0 +1,61 @@
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: snowflake-uds [options]
+
+Serves IDs over a Unix domain socket with a length-prefixed binary protocol:
+send a big-endian u32 count, receive that count followed by as many
+big-endian u64 IDs.
+
+Options:
+  --socket PATH       Socket to listen on [default: /tmp/snowflake.sock]
+  --datacenter N      Datacenter id [default: 0]
+  --machine N         Machine id [default: 0]
+  --epoch EPOCH       RFC 3339 time or Unix milliseconds [default: 2010-11-04T01:42:54.657Z]
+  --layout T,D,M,S    Bit widths of timestamp, datacenter, machine and sequence
+                      [default: 41,5,5,12]";
+
+#[cfg(unix)]
+fn main() -> ExitCode {
+    use std::path::PathBuf;
+
+    use id_gnrt_rust_impl::server::uds::UdsServer;
+    use id_gnrt_rust_impl::server::{run_binary, usage_error};
+
+    // `--socket` stands in for the `--listen` of the TCP servers
+    let mut socket = PathBuf::from("/tmp/snowflake.sock");
+    let mut rest = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--socket=") {
+            socket = path.into();
+        } else if arg == "--socket" {
+            match args.next() {
+                Some(path) => socket = path.into(),
+                None => rest.push(arg),
+            }
+        } else if arg.starts_with("--listen") {
+            return usage_error("snowflake-uds", "use --socket PATH instead of --listen");
+        } else {
+            rest.push(arg);
+        }
+    }
+    run_binary(
+        "snowflake-uds",
+        USAGE,
+        rest,
+        ([127, 0, 0, 1], 0).into(),
+        |_, generator| {
+            let server = UdsServer::bind(&socket, generator)?;
+            eprintln!("listening on {}", server.path().display());
+            Ok(server.serve()?)
+        },
+    )
+}
+
+#[cfg(not(unix))]
+fn main() -> ExitCode {
+    let _ = USAGE;
+    eprintln!("error: snowflake-uds needs Unix domain sockets");
+    ExitCode::FAILURE
+}
@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use id_gnrt_rust_impl::clock::current_timestamp;
+use id_gnrt_rust_impl::epoch::format_rfc3339;
+use id_gnrt_rust_impl::{BitLayout, DecodedId, Epoch, Snowflake, SnowflakeId, decode_with};
+
+const USAGE: &str = "\
+Usage: snowflake <command> [options]
+
+Commands:
+  generate            Generate new IDs
+  decode [ID...]      Decode IDs into their fields; reads stdin without IDs
+  inspect ID...       Check whether IDs are plausible for a layout and epoch;
+                      exits with status 1 if any is not
+
+Options:
+  -n, --count N       Number of IDs to generate [default: 1]
+  -d, --datacenter N  Datacenter id to generate with [default: 0]
+  -m, --machine N     Machine id to generate with [default: 0]
+      --epoch EPOCH   RFC 3339 time or Unix milliseconds [default: 2010-11-04T01:42:54.657Z]
+      --layout T,D,M,S
+                      Bit widths of timestamp, datacenter, machine and sequence
+                      [default: 41,5,5,12]
+  -f, --format FMT    Output as text, json or csv [default: text]
+  -h, --help          Print this help";
+
+/// How long a timestamp may lie in the future before `inspect` flags it
+const FUTURE_TOLERANCE_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Generate,
+    Decode,
+    Inspect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(CliError::Usage(format!("unknown format {:?}", s))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    count: usize,
+    datacenter: u64,
+    machine: u64,
+    epoch: Epoch,
+    layout: BitLayout,
+    format: Format,
+    ids: Vec<String>,
+}
+
+#[derive(Debug)]
+enum CliError {
+    /// Bad command line; printed with a hint to `--help`
+    Usage(String),
+    /// The command itself failed
+    Failed(Box<dyn Error>),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\n\nRun `snowflake --help` for usage.", msg),
+            CliError::Failed(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> From<E> for CliError {
+    fn from(err: E) -> Self {
+        CliError::Failed(Box::new(err))
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let result = parse_args(&args).and_then(|options| run(&options));
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let command = match args[0].as_str() {
+        "generate" => Command::Generate,
+        "decode" => Command::Decode,
+        "inspect" => Command::Inspect,
+        other => return Err(CliError::Usage(format!("unknown command {:?}", other))),
+    };
+    let mut options = Options {
+        command,
+        count: 1,
+        datacenter: 0,
+        machine: 0,
+        epoch: Epoch::TWITTER,
+        layout: BitLayout::DEFAULT,
+        format: Format::Text,
+        ids: Vec::new(),
+    };
+
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if !arg.starts_with('-') {
+            options.ids.push(arg.clone());
+            continue;
+        }
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let value = inline
+            .or_else(|| rest.next().cloned())
+            .ok_or_else(|| CliError::Usage(format!("{} needs a value", flag)))?;
+        match flag {
+            "-n" | "--count" => options.count = parse_flag(flag, &value)?,
+            "-d" | "--datacenter" => options.datacenter = parse_flag(flag, &value)?,
+            "-m" | "--machine" => options.machine = parse_flag(flag, &value)?,
+            "--epoch" => options.epoch = value.parse()?,
+            "--layout" => options.layout = value.parse()?,
+            "-f" | "--format" => options.format = value.parse()?,
+            _ => return Err(CliError::Usage(format!("unknown option {}", flag))),
+        }
+    }
+
+    match command {
+        Command::Generate if !options.ids.is_empty() => Err(CliError::Usage(
+            "generate does not take positional arguments".to_string(),
+        )),
+        Command::Inspect if options.ids.is_empty() => {
+            Err(CliError::Usage("inspect needs at least one id".to_string()))
+        }
+        _ => Ok(options),
+    }
+}
+
+fn parse_flag<T: FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid value {:?} for {}", value, flag)))
+}
+
+/// Run the command, returning whether every ID was plausible
+fn run(options: &Options) -> Result<bool, CliError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match options.command {
+        Command::Generate => {
+            let generator = Snowflake::builder()
+                .datacenter_id(options.datacenter)
+                .machine_id(options.machine)
+                .epoch(options.epoch)
+                .layout(options.layout)
+                .build()?;
+            let ids = generator.next_ids(options.count)?;
+            write_ids(&mut out, options.format, &ids)?;
+            Ok(true)
+        }
+        Command::Decode => {
+            let ids = if options.ids.is_empty() {
+                read_stdin_ids()?
+            } else {
+                parse_ids(&options.ids)?
+            };
+            let decoded: Vec<DecodedId> = ids
+                .iter()
+                .map(|&id| decode_with(id, &options.layout, options.epoch))
+                .collect();
+            write_decoded(&mut out, options.format, &ids, &decoded)?;
+            Ok(true)
+        }
+        Command::Inspect => {
+            let now = current_timestamp();
+            let reports: Vec<(SnowflakeId, Vec<String>)> = parse_ids(&options.ids)?
+                .into_iter()
+                .map(|id| {
+                    (
+                        id,
+                        implausibilities(id, &options.layout, options.epoch, now),
+                    )
+                })
+                .collect();
+            write_inspections(&mut out, options.format, &reports)?;
+            Ok(reports.iter().all(|(_, issues)| issues.is_empty()))
+        }
+    }
+}
+
+fn parse_ids(raw: &[String]) -> Result<Vec<SnowflakeId>, CliError> {
+    raw.iter()
+        .map(|id| {
+            id.parse()
+                .map_err(|err| CliError::Usage(format!("invalid id {:?}: {}", id, err)))
+        })
+        .collect()
+}
+
+fn read_stdin_ids() -> Result<Vec<SnowflakeId>, CliError> {
+    let mut raw = Vec::new();
+    for line in io::stdin().lock().lines() {
+        raw.extend(line?.split_whitespace().map(str::to_string));
+    }
+    parse_ids(&raw)
+}
+
+/// Reasons `id` is unlikely to come from a generator with `layout` and `epoch`
+fn implausibilities(id: SnowflakeId, layout: &BitLayout, epoch: Epoch, now: u64) -> Vec<String> {
+    let mut issues = Vec::new();
+    if id.as_u64() >> 63 != 0 {
+        issues.push("sign bit is set".to_string());
+    }
+    let decoded = decode_with(id, layout, epoch);
+    if decoded.timestamp == epoch.as_millis() {
+        issues.push("timestamp is exactly the epoch".to_string());
+    }
+    if decoded.timestamp > now + FUTURE_TOLERANCE_MS {
+        issues.push(format!(
+            "timestamp {} is {} ms in the future",
+            format_rfc3339(decoded.timestamp),
+            decoded.timestamp - now
+        ));
+    }
+    issues
+}
+
+fn write_ids(out: &mut impl Write, format: Format, ids: &[SnowflakeId]) -> io::Result<()> {
+    match format {
+        Format::Text => {
+            for id in ids {
+                writeln!(out, "{}", id)?;
+            }
+        }
+        Format::Json => {
+            // As strings, since JavaScript cannot represent integers above 2^53
+            let ids: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
+            writeln!(out, "[{}]", ids.join(","))?;
+        }
+        Format::Csv => {
+            writeln!(out, "id")?;
+            for id in ids {
+                writeln!(out, "{}", id)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_decoded(
+    out: &mut impl Write,
+    format: Format,
+    ids: &[SnowflakeId],
+    decoded: &[DecodedId],
+) -> io::Result<()> {
+    let rows = ids.iter().zip(decoded);
+    match format {
+        Format::Text => {
+            for (id, d) in rows {
+                writeln!(
+                    out,
+                    "id = {}, time = {}, ts = {}, dc = {}, mc = {}, seq = {}",
+                    id,
+                    format_rfc3339(d.timestamp),
+                    d.timestamp,
+                    d.datacenter,
+                    d.machine,
+                    d.sequence
+                )?;
+            }
+        }
+        Format::Json => {
+            let objects: Vec<String> = rows
+                .map(|(id, d)| {
+                    format!(
+                        "{{\"id\":\"{}\",\"timestamp\":{},\"time\":\"{}\",\"datacenter\":{},\"machine\":{},\"sequence\":{}}}",
+                        id,
+                        d.timestamp,
+                        format_rfc3339(d.timestamp),
+                        d.datacenter,
+                        d.machine,
+                        d.sequence
+                    )
+                })
+                .collect();
+            writeln!(out, "[{}]", objects.join(","))?;
+        }
+        Format::Csv => {
+            writeln!(out, "id,timestamp,time,datacenter,machine,sequence")?;
+            for (id, d) in rows {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    id,
+                    d.timestamp,
+                    format_rfc3339(d.timestamp),
+                    d.datacenter,
+                    d.machine,
+                    d.sequence
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_inspections(
+    out: &mut impl Write,
+    format: Format,
+    reports: &[(SnowflakeId, Vec<String>)],
+) -> io::Result<()> {
+    match format {
+        Format::Text => {
+            for (id, issues) in reports {
+                if issues.is_empty() {
+                    writeln!(out, "{}: plausible", id)?;
+                } else {
+                    writeln!(out, "{}: implausible ({})", id, issues.join("; "))?;
+                }
+            }
+        }
+        Format::Json => {
+            let objects: Vec<String> = reports
+                .iter()
+                .map(|(id, issues)| {
+                    let issues: Vec<String> = issues
+                        .iter()
+                        .map(|issue| format!("\"{}\"", issue))
+                        .collect();
+                    format!(
+                        "{{\"id\":\"{}\",\"plausible\":{},\"issues\":[{}]}}",
+                        id,
+                        issues.is_empty(),
+                        issues.join(",")
+                    )
+                })
+                .collect();
+            writeln!(out, "[{}]", objects.join(","))?;
+        }
+        Format::Csv => {
+            writeln!(out, "id,plausible,issues")?;
+            for (id, issues) in reports {
+                writeln!(
+                    out,
+                    "{},{},\"{}\"",
+                    id,
+                    issues.is_empty(),
+                    issues.join("; ")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! Fallible construction of [`Snowflake`] generators.
+
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+
+use crate::clock::{Clock, SystemClock};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::Snowflake;
+use crate::layout::BitLayout;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::node::NodeId;
+use crate::persist::StateFile;
+use crate::rollback::{RollbackEvent, RollbackListener, RollbackPolicy};
+
+/// Builder for [`Snowflake`] that validates its configuration
+///
+/// ```
+/// use id_gnrt_rust_impl::{Snowflake, SnowflakeError};
+///
+/// let generator = Snowflake::builder().datacenter_id(1).machine_id(2).build()?;
+/// assert!(Snowflake::builder().machine_id(1000).build().is_err());
+/// # Ok::<(), SnowflakeError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SnowflakeBuilder<C = SystemClock> {
+    pub(crate) datacenter_id: u64,
+    pub(crate) machine_id: u64,
+    pub(crate) layout: BitLayout,
+    pub(crate) epoch: Epoch,
+    pub(crate) clock: C,
+    pub(crate) rollback_policy: RollbackPolicy,
+    pub(crate) on_rollback: RollbackListener,
+    pub(crate) state_file: Option<StateFile>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<Arc<Metrics>>,
+}
+
+impl SnowflakeBuilder {
+    /// Create a builder for datacenter 0, machine 0, the default layout, the
+    /// Twitter epoch and the system clock
+    pub fn new() -> Self {
+        SnowflakeBuilder {
+            datacenter_id: 0,
+            machine_id: 0,
+            layout: BitLayout::DEFAULT,
+            epoch: Epoch::TWITTER,
+            clock: SystemClock,
+            rollback_policy: RollbackPolicy::default(),
+            on_rollback: RollbackListener::default(),
+            state_file: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+}
+
+impl Default for SnowflakeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> SnowflakeBuilder<C> {
+    /// Set the datacenter id
+    pub fn datacenter_id(mut self, datacenter_id: u64) -> Self {
+        self.datacenter_id = datacenter_id;
+        self
+    }
+
+    /// Set the machine id
+    pub fn machine_id(mut self, machine_id: u64) -> Self {
+        self.machine_id = machine_id;
+        self
+    }
+
+    /// Set the datacenter and machine ids from a derived [`NodeId`]
+    ///
+    /// `build` still checks them against the builder's layout, in case the
+    /// node id was derived for another one.
+    pub fn node_id(self, node: NodeId) -> Self {
+        self.datacenter_id(node.datacenter_id())
+            .machine_id(node.machine_id())
+    }
+
+    /// Set the bit layout of the issued IDs
+    pub fn layout(mut self, layout: BitLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the epoch that ID timestamps are counted from
+    pub fn epoch(mut self, epoch: Epoch) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Read time from `clock` instead of the system clock
+    pub fn clock<D: Clock>(self, clock: D) -> SnowflakeBuilder<D> {
+        SnowflakeBuilder {
+            datacenter_id: self.datacenter_id,
+            machine_id: self.machine_id,
+            layout: self.layout,
+            epoch: self.epoch,
+            clock,
+            rollback_policy: self.rollback_policy,
+            on_rollback: self.on_rollback,
+            state_file: self.state_file,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics,
+        }
+    }
+
+    /// Choose what happens when the clock moves backwards
+    pub fn rollback_policy(mut self, policy: RollbackPolicy) -> Self {
+        self.rollback_policy = policy;
+        self
+    }
+
+    /// Call `listener` for every clock rollback the generator sees
+    pub fn on_rollback(
+        mut self,
+        listener: impl Fn(&RollbackEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_rollback = RollbackListener::new(listener);
+        self
+    }
+
+    /// Persist a high-water timestamp to `file` and never issue IDs below
+    /// the one found there at startup
+    pub fn state_file(mut self, file: StateFile) -> Self {
+        self.state_file = Some(file);
+        self
+    }
+
+    /// Record into `metrics` instead of a registry of the generator's own
+    ///
+    /// Generators sharing one [`Metrics`] are reported as a whole.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Validate the configuration and create the generator
+    pub fn build(self) -> Result<Snowflake<C>, SnowflakeError> {
+        if self.datacenter_id > self.layout.max_datacenter() {
+            return Err(SnowflakeError::DatacenterIdOutOfRange {
+                id: self.datacenter_id,
+                max: self.layout.max_datacenter(),
+            });
+        }
+        if self.machine_id > self.layout.max_machine() {
+            return Err(SnowflakeError::MachineIdOutOfRange {
+                id: self.machine_id,
+                max: self.layout.max_machine(),
+            });
+        }
+
+        Snowflake::from_builder(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{MAX_DATACENTER, MAX_MACHINE};
+
+    #[test]
+    fn test_build_valid() {
+        let generator = SnowflakeBuilder::new()
+            .datacenter_id(MAX_DATACENTER)
+            .machine_id(MAX_MACHINE)
+            .build()
+            .unwrap();
+        let decoded = Snowflake::decode(generator.next_id().unwrap());
+        assert_eq!(
+            (decoded.datacenter, decoded.machine),
+            (MAX_DATACENTER, MAX_MACHINE)
+        );
+    }
+
+    #[test]
+    fn test_build_out_of_range() {
+        assert_eq!(
+            SnowflakeBuilder::new()
+                .datacenter_id(MAX_DATACENTER + 1)
+                .build()
+                .err(),
+            Some(SnowflakeError::DatacenterIdOutOfRange {
+                id: MAX_DATACENTER + 1,
+                max: MAX_DATACENTER
+            })
+        );
+        assert_eq!(
+            SnowflakeBuilder::new()
+                .machine_id(MAX_MACHINE + 1)
+                .build()
+                .err(),
+            Some(SnowflakeError::MachineIdOutOfRange {
+                id: MAX_MACHINE + 1,
+                max: MAX_MACHINE
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_checks_against_layout() {
+        let layout = BitLayout::new(41, 0, 10, 12).unwrap();
+        assert!(
+            SnowflakeBuilder::new()
+                .layout(layout)
+                .machine_id(1023)
+                .build()
+                .is_ok()
+        );
+        assert_eq!(
+            SnowflakeBuilder::new()
+                .layout(layout)
+                .datacenter_id(1)
+                .build()
+                .err(),
+            Some(SnowflakeError::DatacenterIdOutOfRange { id: 1, max: 0 })
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Time sources for the generator.
+//!
+//! The generator reads time through the [`Clock`] trait so that tests can
+//! drive it with a [`ManualClock`] instead of the wall clock.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A source of wall-clock time in milliseconds since the Unix epoch
+pub trait Clock: Send + Sync {
+    /// Current time in milliseconds since the Unix epoch
+    fn now_millis(&self) -> u64;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now_millis(&self) -> u64 {
+        (**self).now_millis()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_millis(&self) -> u64 {
+        (**self).now_millis()
+    }
+}
+
+/// Reads [`SystemTime::now`] on every call
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        current_timestamp()
+    }
+}
+
+/// Reads the system time once and then advances with [`Instant`]
+///
+/// Steps of the wall clock after creation, such as NTP corrections, do not
+/// affect this clock, so it never runs backwards.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    anchor_millis: u64,
+    anchor: Instant,
+}
+
+impl MonotonicClock {
+    /// Anchor a new clock at the current system time
+    pub fn new() -> Self {
+        MonotonicClock {
+            anchor_millis: current_timestamp(),
+            anchor: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_millis(&self) -> u64 {
+        self.anchor_millis + self.anchor.elapsed().as_millis() as u64
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// ```
+/// use std::time::Duration;
+/// use id_gnrt_rust_impl::clock::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new(1_700_000_000_000);
+/// clock.advance(Duration::from_millis(5));
+/// clock.rewind(Duration::from_millis(2));
+/// assert_eq!(clock.now_millis(), 1_700_000_000_003);
+/// ```
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+impl ManualClock {
+    /// Create a clock reading `millis` since the Unix epoch
+    pub fn new(millis: u64) -> Self {
+        ManualClock {
+            now: AtomicU64::new(millis),
+        }
+    }
+
+    /// Set the time to `millis` since the Unix epoch
+    pub fn set(&self, millis: u64) {
+        self.now.store(millis, Ordering::SeqCst);
+    }
+
+    /// Move the clock forwards
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Move the clock backwards, stopping at the Unix epoch
+    pub fn rewind(&self, by: Duration) {
+        let by = by.as_millis() as u64;
+        let _ = self
+            .now
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |now| {
+                Some(now.saturating_sub(by))
+            });
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Get current timestamp in milliseconds since the Unix epoch
+///
+/// A clock set before 1970 reads as 0, which the generator rejects as being
+/// before its epoch.
+pub fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis() as u64)
+}
+
+/// Wait until `clock` has moved past `last`
+pub fn wait_next_millis<C: Clock + ?Sized>(clock: &C, last: u64) -> u64 {
+    let mut ts = clock.now_millis();
+    while ts <= last {
+        std::hint::spin_loop();
+        ts = clock.now_millis();
+    }
+    ts
+}
+
+/// Wait until `clock` has moved past `last`, giving up at `deadline`
+pub fn wait_next_millis_until<C: Clock + ?Sized>(
+    clock: &C,
+    last: u64,
+    deadline: Instant,
+) -> Option<u64> {
+    loop {
+        let ts = clock.now_millis();
+        if ts > last {
+            return Some(ts);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_clock_tracks_system_time() {
+        let clock = MonotonicClock::new();
+        let drift = clock.now_millis().abs_diff(SystemClock.now_millis());
+        assert!(drift < 1000, "drifted {} ms", drift);
+    }
+
+    #[test]
+    fn test_manual_clock_rewind_saturates() {
+        let clock = ManualClock::new(10);
+        clock.rewind(Duration::from_millis(20));
+        assert_eq!(clock.now_millis(), 0);
+        clock.set(42);
+        assert_eq!(Arc::new(clock).now_millis(), 42);
+    }
+
+    #[test]
+    fn test_wait_until_deadline() {
+        let clock = ManualClock::new(10);
+        assert_eq!(wait_next_millis_until(&clock, 9, Instant::now()), Some(10));
+        let deadline = Instant::now() + Duration::from_millis(5);
+        assert_eq!(wait_next_millis_until(&clock, 10, deadline), None);
+        assert!(Instant::now() >= deadline);
+    }
+}
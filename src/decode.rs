@@ -0,0 +1,183 @@
+//! Decoding of Snowflake IDs back into their components.
+
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::id::SnowflakeId;
+use crate::layout::BitLayout;
+
+/// The fields of a Snowflake ID, together with the layout and epoch they
+/// were read with
+///
+/// ```
+/// use id_gnrt_rust_impl::{Snowflake, decode};
+///
+/// let id = Snowflake::new(2, 3).next_id()?;
+/// let decoded = decode(id);
+/// assert_eq!((decoded.datacenter, decoded.machine), (2, 3));
+/// assert_eq!(decoded.encode()?, id);
+/// # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodedId {
+    /// Creation time in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub datacenter: u64,
+    pub machine: u64,
+    pub sequence: u64,
+    pub layout: BitLayout,
+    pub epoch: Epoch,
+}
+
+impl DecodedId {
+    /// Assemble the fields back into an ID, checking that each one fits
+    pub fn encode(&self) -> Result<SnowflakeId, SnowflakeError> {
+        let epoch = self.epoch.as_millis();
+        let timestamp =
+            self.timestamp
+                .checked_sub(epoch)
+                .ok_or(SnowflakeError::ClockBeforeEpoch {
+                    now: self.timestamp,
+                    epoch,
+                })?;
+        if timestamp > self.layout.max_timestamp() {
+            return Err(SnowflakeError::TimestampOverflow {
+                timestamp,
+                max: self.layout.max_timestamp(),
+            });
+        }
+        if self.datacenter > self.layout.max_datacenter() {
+            return Err(SnowflakeError::DatacenterIdOutOfRange {
+                id: self.datacenter,
+                max: self.layout.max_datacenter(),
+            });
+        }
+        if self.machine > self.layout.max_machine() {
+            return Err(SnowflakeError::MachineIdOutOfRange {
+                id: self.machine,
+                max: self.layout.max_machine(),
+            });
+        }
+        if self.sequence > self.layout.max_sequence() {
+            return Err(SnowflakeError::SequenceOutOfRange {
+                sequence: self.sequence,
+                max: self.layout.max_sequence(),
+            });
+        }
+
+        Ok(SnowflakeId::from_u64(self.layout.compose(
+            timestamp,
+            self.datacenter,
+            self.machine,
+            self.sequence,
+        )))
+    }
+}
+
+/// Decode an ID with the default 41/5/5/12 layout and Twitter epoch
+pub fn decode(id: SnowflakeId) -> DecodedId {
+    decode_with(id, &BitLayout::DEFAULT, Epoch::TWITTER)
+}
+
+/// Decode an ID produced with `layout` and `epoch`
+pub fn decode_with(id: SnowflakeId, layout: &BitLayout, epoch: Epoch) -> DecodedId {
+    let id = id.as_u64();
+    DecodedId {
+        timestamp: ((id >> layout.timestamp_shift()) & layout.max_timestamp()) + epoch.as_millis(),
+        datacenter: (id >> layout.datacenter_shift()) & layout.max_datacenter(),
+        machine: (id >> layout.machine_shift()) & layout.max_machine(),
+        sequence: id & layout.max_sequence(),
+        layout: *layout,
+        epoch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::layout::{CUSTOM_EPOCH, DATACENTER_SHIFT, MACHINE_SHIFT, TIMESTAMP_SHIFT};
+
+    #[test]
+    fn test_decode_with_latest_epoch() {
+        let decoded = decode_with(
+            SnowflakeId::from_u64(i64::MAX as u64),
+            &BitLayout::DEFAULT,
+            Epoch::MAX,
+        );
+        assert_eq!(
+            decoded.timestamp,
+            Epoch::MAX.as_millis() + BitLayout::DEFAULT.max_timestamp()
+        );
+        assert_eq!(decoded.encode(), Ok(SnowflakeId::from_u64(i64::MAX as u64)));
+    }
+
+    #[test]
+    fn test_decode_components() {
+        let id = (42 << TIMESTAMP_SHIFT) | (3 << DATACENTER_SHIFT) | (7 << MACHINE_SHIFT) | 99;
+        let decoded = decode(SnowflakeId::from_u64(id));
+        assert_eq!(decoded.timestamp, CUSTOM_EPOCH + 42);
+        assert_eq!(decoded.datacenter, 3);
+        assert_eq!(decoded.machine, 7);
+        assert_eq!(decoded.sequence, 99);
+        assert_eq!(decoded.layout, BitLayout::DEFAULT);
+        assert_eq!(decoded.epoch, Epoch::TWITTER);
+        assert_eq!(decoded.encode(), Ok(SnowflakeId::from_u64(id)));
+    }
+
+    #[test]
+    fn test_decode_custom_layout() {
+        let layout = BitLayout::new(43, 0, 10, 10).unwrap();
+        let id = SnowflakeId::from_u64(layout.compose(42, 0, 1023, 1023));
+        let decoded = decode_with(id, &layout, Epoch::UNIX);
+        assert_eq!(
+            (
+                decoded.timestamp,
+                decoded.datacenter,
+                decoded.machine,
+                decoded.sequence
+            ),
+            (42, 0, 1023, 1023)
+        );
+        assert_eq!(decoded.encode(), Ok(id));
+    }
+
+    #[test]
+    fn test_encode_rejects_fields_out_of_range() {
+        let decoded = decode(SnowflakeId::from_u64(0));
+        assert_eq!(
+            DecodedId {
+                sequence: 4096,
+                ..decoded
+            }
+            .encode(),
+            Err(SnowflakeError::SequenceOutOfRange {
+                sequence: 4096,
+                max: 4095
+            })
+        );
+        assert!(
+            DecodedId {
+                machine: 32,
+                ..decoded
+            }
+            .encode()
+            .is_err()
+        );
+        assert!(
+            DecodedId {
+                timestamp: 0,
+                ..decoded
+            }
+            .encode()
+            .is_err()
+        );
+        assert!(
+            DecodedId {
+                timestamp: u64::MAX,
+                ..decoded
+            }
+            .encode()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Compact text encodings of [`SnowflakeId`].
+//!
+//! Base62, base58 and Crockford base32 drop leading zeros, so their length
+//! varies with the ID. Hex is always 16 digits.
+//!
+//! The fixed-width forms, hex, [`to_padded_decimal`] and
+//! [`to_sortable_base32`], sort byte-wise in the same order as the IDs
+//! themselves, which makes them suitable as keys in ordered stores.
+//!
+//! [`to_padded_decimal`]: SnowflakeId::to_padded_decimal
+//! [`to_sortable_base32`]: SnowflakeId::to_sortable_base32
+
+use crate::id::{ParseIdError, SnowflakeId};
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const DECIMAL: &[u8; 10] = b"0123456789";
+
+/// Marks bytes outside an alphabet in a decoding table
+const INVALID: u8 = u8::MAX;
+
+const BASE62_TABLE: [u8; 256] = table(BASE62);
+const BASE58_TABLE: [u8; 256] = table(BASE58);
+const CROCKFORD_TABLE: [u8; 256] = crockford_table();
+const HEX_TABLE: [u8; 256] = hex_table();
+const DECIMAL_TABLE: [u8; 256] = table(DECIMAL);
+
+/// Width of the hex form
+const HEX_WIDTH: usize = 16;
+/// Width of the padded decimal form, enough for `u64::MAX`
+const DECIMAL_WIDTH: usize = 20;
+/// Width of the sortable base32 form, enough for `u64::MAX`
+const BASE32_WIDTH: usize = 13;
+
+impl SnowflakeId {
+    /// Encode with the digits `0-9A-Za-z`
+    pub fn to_base62(self) -> String {
+        encode(self.as_u64(), BASE62)
+    }
+
+    /// Parse the output of [`to_base62`](Self::to_base62)
+    pub fn from_base62(s: &str) -> Result<Self, ParseIdError> {
+        decode(s, 62, &BASE62_TABLE).map(SnowflakeId::from_u64)
+    }
+
+    /// Encode with the Bitcoin base58 alphabet, which leaves out `0`, `O`,
+    /// `I` and `l`
+    pub fn to_base58(self) -> String {
+        encode(self.as_u64(), BASE58)
+    }
+
+    /// Parse the output of [`to_base58`](Self::to_base58)
+    pub fn from_base58(s: &str) -> Result<Self, ParseIdError> {
+        decode(s, 58, &BASE58_TABLE).map(SnowflakeId::from_u64)
+    }
+
+    /// Encode with Crockford's base32 alphabet, in upper case
+    pub fn to_base32(self) -> String {
+        encode(self.as_u64(), CROCKFORD)
+    }
+
+    /// Parse Crockford base32
+    ///
+    /// Lower case is accepted, `I` and `L` read as `1`, `O` reads as `0` and
+    /// hyphens are ignored.
+    pub fn from_base32(s: &str) -> Result<Self, ParseIdError> {
+        let digits: String = s.chars().filter(|&ch| ch != '-').collect();
+        decode(&digits, 32, &CROCKFORD_TABLE).map(SnowflakeId::from_u64)
+    }
+
+    /// Encode as 16 lower-case hex digits
+    pub fn to_hex(self) -> String {
+        format!("{:016x}", self.as_u64())
+    }
+
+    /// Parse exactly 16 hex digits in either case
+    pub fn from_hex(s: &str) -> Result<Self, ParseIdError> {
+        check_width(s, HEX_WIDTH)?;
+        decode(s, 16, &HEX_TABLE).map(SnowflakeId::from_u64)
+    }
+
+    /// Encode as 20 decimal digits, zero-padded on the left
+    pub fn to_padded_decimal(self) -> String {
+        format!("{:020}", self.as_u64())
+    }
+
+    /// Parse exactly 20 decimal digits
+    pub fn from_padded_decimal(s: &str) -> Result<Self, ParseIdError> {
+        check_width(s, DECIMAL_WIDTH)?;
+        decode(s, 10, &DECIMAL_TABLE).map(SnowflakeId::from_u64)
+    }
+
+    /// Encode as 13 Crockford base32 digits, zero-padded on the left
+    pub fn to_sortable_base32(self) -> String {
+        let digits = encode(self.as_u64(), CROCKFORD);
+        format!("{:0>width$}", digits, width = BASE32_WIDTH)
+    }
+
+    /// Parse exactly 13 Crockford base32 digits
+    pub fn from_sortable_base32(s: &str) -> Result<Self, ParseIdError> {
+        check_width(s, BASE32_WIDTH)?;
+        decode(s, 32, &CROCKFORD_TABLE).map(SnowflakeId::from_u64)
+    }
+}
+
+/// Reject non-empty input that is not exactly `width` characters long
+fn check_width(s: &str, width: usize) -> Result<(), ParseIdError> {
+    let len = s.chars().count();
+    if len != 0 && len != width {
+        return Err(ParseIdError::InvalidLength {
+            len,
+            expected: width,
+        });
+    }
+    Ok(())
+}
+
+/// Write `n` in the base of `digits` without leading zeros
+pub(crate) fn encode(mut n: u64, digits: &[u8]) -> String {
+    let base = digits.len() as u64;
+    let mut out = Vec::with_capacity(16);
+    loop {
+        out.push(digits[(n % base) as usize]);
+        n /= base;
+        if n == 0 {
+            break;
+        }
+    }
+    out.reverse();
+    String::from_utf8(out).expect("alphabets are ASCII")
+}
+
+/// Read `s` as a number in `base`, looking digits up in `table`
+pub(crate) fn decode(s: &str, base: u64, table: &[u8; 256]) -> Result<u64, ParseIdError> {
+    if s.is_empty() {
+        return Err(ParseIdError::Empty);
+    }
+    let mut n: u64 = 0;
+    for (index, ch) in s.char_indices() {
+        let digit = u8::try_from(ch)
+            .map(|b| table[b as usize])
+            .unwrap_or(INVALID);
+        if digit == INVALID {
+            return Err(ParseIdError::InvalidDigit { ch, index });
+        }
+        n = n
+            .checked_mul(base)
+            .and_then(|n| n.checked_add(u64::from(digit)))
+            .ok_or(ParseIdError::Overflow)?;
+    }
+    Ok(n)
+}
+
+/// Build a decoding table mapping each byte of `digits` to its position
+const fn table(digits: &[u8]) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < digits.len() {
+        table[digits[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+const fn crockford_table() -> [u8; 256] {
+    let mut table = table(CROCKFORD);
+    let mut i = 0;
+    while i < CROCKFORD.len() {
+        table[CROCKFORD[i].to_ascii_lowercase() as usize] = i as u8;
+        i += 1;
+    }
+    table[b'O' as usize] = 0;
+    table[b'o' as usize] = 0;
+    table[b'I' as usize] = 1;
+    table[b'i' as usize] = 1;
+    table[b'L' as usize] = 1;
+    table[b'l' as usize] = 1;
+    table
+}
+
+const fn hex_table() -> [u8; 256] {
+    let mut table = table(HEX);
+    let mut i = 10;
+    while i < HEX.len() {
+        table[HEX[i].to_ascii_uppercase() as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+/// Deterministic pseudo-random IDs covering the whole `u64` range
+#[cfg(test)]
+pub(crate) fn sample_ids() -> impl Iterator<Item = u64> {
+    let edges = [
+        0,
+        1,
+        57,
+        58,
+        61,
+        62,
+        u32::MAX as u64,
+        i64::MAX as u64,
+        u64::MAX - 1,
+        u64::MAX,
+    ];
+    let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+    let random = std::iter::repeat_with(move || {
+        // splitmix64
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        let z = z ^ (z >> 31);
+        // Vary the magnitude so short encodings are covered too
+        z >> (z % 64)
+    });
+    edges.into_iter().chain(random.take(10_000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_full_range() {
+        for raw in sample_ids() {
+            let id = SnowflakeId::from_u64(raw);
+            assert_eq!(SnowflakeId::from_base62(&id.to_base62()), Ok(id));
+            assert_eq!(SnowflakeId::from_base58(&id.to_base58()), Ok(id));
+            assert_eq!(SnowflakeId::from_base32(&id.to_base32()), Ok(id));
+            assert_eq!(SnowflakeId::from_hex(&id.to_hex()), Ok(id));
+            assert_eq!(
+                SnowflakeId::from_padded_decimal(&id.to_padded_decimal()),
+                Ok(id)
+            );
+            assert_eq!(
+                SnowflakeId::from_sortable_base32(&id.to_sortable_base32()),
+                Ok(id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixed_width_forms_sort_like_ids() {
+        let ids: Vec<SnowflakeId> = sample_ids().map(SnowflakeId::from_u64).collect();
+        for pair in ids.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.to_padded_decimal().len(), 20);
+            assert_eq!(a.to_sortable_base32().len(), 13);
+            assert_eq!(a.to_padded_decimal().cmp(&b.to_padded_decimal()), a.cmp(&b));
+            assert_eq!(
+                a.to_sortable_base32().cmp(&b.to_sortable_base32()),
+                a.cmp(&b)
+            );
+            assert_eq!(a.to_hex().cmp(&b.to_hex()), a.cmp(&b));
+        }
+
+        let mut by_text = ids.clone();
+        by_text.sort_by_key(|id| id.to_sortable_base32());
+        let mut by_value = ids;
+        by_value.sort();
+        assert_eq!(by_text, by_value);
+    }
+
+    #[test]
+    fn test_known_encodings() {
+        let max = SnowflakeId::from_u64(u64::MAX);
+        assert_eq!(max.to_base62(), "LygHa16AHYF");
+        assert_eq!(max.to_base58(), "jpXCZedGfVQ");
+        assert_eq!(max.to_base32(), "FZZZZZZZZZZZZ");
+        assert_eq!(max.to_hex(), "ffffffffffffffff");
+        assert_eq!(SnowflakeId::from_u64(0).to_base58(), "1");
+    }
+
+    #[test]
+    fn test_crockford_aliases() {
+        assert_eq!(
+            SnowflakeId::from_base32("1o-Il"),
+            SnowflakeId::from_base32("1011")
+        );
+        assert_eq!(
+            SnowflakeId::from_base32("zz"),
+            Ok(SnowflakeId::from_u64(1023))
+        );
+        assert_eq!(
+            SnowflakeId::from_base32("U"),
+            Err(ParseIdError::InvalidDigit { ch: 'U', index: 0 })
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert_eq!(SnowflakeId::from_base62(""), Err(ParseIdError::Empty));
+        assert_eq!(
+            SnowflakeId::from_base62("ab-c"),
+            Err(ParseIdError::InvalidDigit { ch: '-', index: 2 })
+        );
+        assert_eq!(
+            SnowflakeId::from_base58("10"),
+            Err(ParseIdError::InvalidDigit { ch: '0', index: 1 })
+        );
+        assert_eq!(
+            SnowflakeId::from_base58("é"),
+            Err(ParseIdError::InvalidDigit { ch: 'é', index: 0 })
+        );
+        assert_eq!(
+            SnowflakeId::from_base62("LygHa16AHYG"),
+            Err(ParseIdError::Overflow)
+        );
+        assert_eq!(
+            SnowflakeId::from_base32("G000000000000"),
+            Err(ParseIdError::Overflow)
+        );
+        assert_eq!(
+            SnowflakeId::from_hex("ff"),
+            Err(ParseIdError::InvalidLength {
+                len: 2,
+                expected: 16
+            })
+        );
+        assert_eq!(
+            SnowflakeId::from_hex("00000000000000g0"),
+            Err(ParseIdError::InvalidDigit { ch: 'g', index: 14 })
+        );
+        assert_eq!(
+            SnowflakeId::from_hex("FFFFFFFFFFFFFFFF"),
+            Ok(SnowflakeId::from_u64(u64::MAX))
+        );
+        assert_eq!(
+            SnowflakeId::from_padded_decimal("18446744073709551616"),
+            Err(ParseIdError::Overflow)
+        );
+        assert_eq!(
+            SnowflakeId::from_padded_decimal("42"),
+            Err(ParseIdError::InvalidLength {
+                len: 2,
+                expected: 20
+            })
+        );
+        assert_eq!(
+            SnowflakeId::from_sortable_base32("0000000000001"),
+            Ok(SnowflakeId::from_u64(1))
+        );
+    }
+}
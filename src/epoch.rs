@@ -0,0 +1,284 @@
+//! The reference point that ID timestamps are counted from.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::SnowflakeError;
+use crate::layout::CUSTOM_EPOCH;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Epoch of a generator, in milliseconds since the Unix epoch
+///
+/// A later epoch leaves more of the timestamp bits for the future. Epochs
+/// can be written as RFC 3339 timestamps or dates, or as plain milliseconds,
+/// and lie at most [`Epoch::MAX`] after the Unix epoch so that adding any
+/// timestamp a layout can hold never overflows.
+///
+/// ```
+/// use id_gnrt_rust_impl::Epoch;
+///
+/// let epoch: Epoch = "2024-01-01T00:00:00Z".parse()?;
+/// assert_eq!(epoch.as_millis(), 1_704_067_200_000);
+/// assert_eq!("2024-01-01".parse::<Epoch>()?, epoch);
+/// assert_eq!(epoch.to_string(), "2024-01-01T00:00:00.000Z");
+/// # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Epoch(u64);
+
+impl Epoch {
+    /// Twitter custom epoch: Nov 04 2010 01:42:54 UTC
+    pub const TWITTER: Epoch = Epoch(CUSTOM_EPOCH);
+    /// The Unix epoch itself
+    pub const UNIX: Epoch = Epoch(0);
+    /// The latest epoch, `i64::MAX` milliseconds after the Unix epoch
+    pub const MAX: Epoch = Epoch(i64::MAX as u64);
+
+    /// Create an epoch from milliseconds since the Unix epoch
+    ///
+    /// # Panics
+    ///
+    /// Panics if `millis` is past [`Epoch::MAX`]. Use
+    /// [`Epoch::try_from_millis`] to handle that as an error instead.
+    pub const fn from_millis(millis: u64) -> Self {
+        assert!(millis <= Epoch::MAX.0, "epoch is past Epoch::MAX");
+        Epoch(millis)
+    }
+
+    /// Create an epoch from milliseconds since the Unix epoch, failing if
+    /// `millis` is past [`Epoch::MAX`]
+    pub const fn try_from_millis(millis: u64) -> Result<Self, SnowflakeError> {
+        if millis > Epoch::MAX.0 {
+            return Err(SnowflakeError::InvalidEpoch("milliseconds out of range"));
+        }
+        Ok(Epoch(millis))
+    }
+
+    /// Milliseconds since the Unix epoch
+    pub const fn as_millis(&self) -> u64 {
+        self.0
+    }
+
+    /// Parse an RFC 3339 timestamp such as `2024-01-01T00:00:00Z` or a bare
+    /// `2024-01-01` date, which is taken as midnight UTC
+    pub fn from_rfc3339(s: &str) -> Result<Self, SnowflakeError> {
+        let millis = parse_rfc3339(s)?;
+        u64::try_from(millis)
+            .map(Epoch)
+            .map_err(|_| SnowflakeError::InvalidEpoch("epoch is before 1970"))
+    }
+}
+
+impl Default for Epoch {
+    fn default() -> Self {
+        Epoch::TWITTER
+    }
+}
+
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_rfc3339(self.0))
+    }
+}
+
+impl FromStr for Epoch {
+    type Err = SnowflakeError;
+
+    /// Accepts milliseconds since the Unix epoch or an RFC 3339 timestamp
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return s
+                .parse()
+                .map_err(|_| SnowflakeError::InvalidEpoch("milliseconds out of range"))
+                .and_then(Epoch::try_from_millis);
+        }
+        Epoch::from_rfc3339(s)
+    }
+}
+
+/// Format milliseconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SS.mmmZ`
+pub fn format_rfc3339(millis: u64) -> String {
+    let days = (millis / MILLIS_PER_DAY as u64) as i64;
+    let rem = millis % MILLIS_PER_DAY as u64;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3_600_000,
+        rem / 60_000 % 60,
+        rem / 1000 % 60,
+        rem % 1000
+    )
+}
+
+/// Parse an RFC 3339 timestamp or full date into signed Unix milliseconds
+fn parse_rfc3339(s: &str) -> Result<i64, SnowflakeError> {
+    let invalid = SnowflakeError::InvalidEpoch("expected an RFC 3339 timestamp");
+    let bytes = s.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return Err(invalid);
+    }
+    let year = digits(&bytes[0..4]).ok_or(invalid.clone())?;
+    let month = digits(&bytes[5..7]).ok_or(invalid.clone())?;
+    let day = digits(&bytes[8..10]).ok_or(invalid.clone())?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(SnowflakeError::InvalidEpoch("date out of range"));
+    }
+    let date = days_from_civil(year, month, day) * MILLIS_PER_DAY;
+    if bytes.len() == 10 {
+        return Ok(date);
+    }
+
+    // THH:MM:SS
+    if bytes.len() < 20 || !matches!(bytes[10], b'T' | b't' | b' ') {
+        return Err(invalid);
+    }
+    if bytes[13] != b':' || bytes[16] != b':' {
+        return Err(invalid);
+    }
+    let hour = digits(&bytes[11..13]).ok_or(invalid.clone())?;
+    let minute = digits(&bytes[14..16]).ok_or(invalid.clone())?;
+    let second = digits(&bytes[17..19]).ok_or(invalid.clone())?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(SnowflakeError::InvalidEpoch("time out of range"));
+    }
+
+    // Optional fraction, truncated to milliseconds
+    let mut rest = &bytes[19..];
+    let mut millis = 0;
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let len = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return Err(invalid);
+        }
+        for (i, b) in fraction[..len.min(3)].iter().enumerate() {
+            millis += i64::from(b - b'0') * [100, 10, 1][i];
+        }
+        rest = &fraction[len..];
+    }
+
+    // Z or +HH:MM / -HH:MM
+    let offset = match rest {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hours = digits(&[*h1, *h2]).ok_or(invalid.clone())?;
+            let minutes = digits(&[*m1, *m2]).ok_or(invalid.clone())?;
+            if hours > 23 || minutes > 59 {
+                return Err(SnowflakeError::InvalidEpoch("offset out of range"));
+            }
+            let offset = (hours * 60 + minutes) * 60_000;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return Err(invalid),
+    };
+
+    Ok(date + ((hour * 60 + minute) * 60 + second) * 1000 + millis - offset)
+}
+
+/// Parse a run of ASCII digits
+fn digits(bytes: &[u8]) -> Option<i64> {
+    bytes.iter().try_fold(0, |acc, b| {
+        b.is_ascii_digit().then(|| acc * 10 + i64::from(b - b'0'))
+    })
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_twitter_epoch_roundtrip() {
+        let epoch: Epoch = "2010-11-04T01:42:54.657Z".parse().unwrap();
+        assert_eq!(epoch, Epoch::TWITTER);
+        assert_eq!(epoch.to_string(), "2010-11-04T01:42:54.657Z");
+    }
+
+    #[test]
+    fn test_offsets_and_fractions() {
+        assert_eq!(
+            Epoch::from_rfc3339("2024-01-01T02:30:00.123456+02:30").unwrap(),
+            Epoch::from_millis(1_704_067_200_123)
+        );
+        assert_eq!(
+            Epoch::from_rfc3339("1969-12-31T20:00:00-04:00").unwrap(),
+            Epoch::UNIX
+        );
+        assert_eq!(
+            "1700000000000".parse(),
+            Ok(Epoch::from_millis(1_700_000_000_000))
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        for input in [
+            "",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00.Z",
+            "2024/01/01",
+            "1969-12-31",
+            "9223372036854775808",
+            "18446744073709551000",
+        ] {
+            assert!(
+                input.parse::<Epoch>().is_err(),
+                "{:?} should be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_epoch() {
+        assert_eq!(Epoch::try_from_millis(i64::MAX as u64), Ok(Epoch::MAX));
+        assert!(Epoch::try_from_millis(i64::MAX as u64 + 1).is_err());
+        assert_eq!("9223372036854775807".parse(), Ok(Epoch::MAX));
+    }
+
+    #[test]
+    fn test_civil_roundtrip() {
+        for days in [-719_468, -1, 0, 1, 11_016, 19_723, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Error type shared by the generator and its builder.
+
+use std::error::Error;
+use std::fmt;
+
+/// Errors reported while configuring a generator or issuing IDs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnowflakeError {
+    /// The datacenter id does not fit in the datacenter bits
+    DatacenterIdOutOfRange { id: u64, max: u64 },
+    /// The machine id does not fit in the machine bits
+    MachineIdOutOfRange { id: u64, max: u64 },
+    /// The sequence number does not fit in the sequence bits
+    SequenceOutOfRange { sequence: u64, max: u64 },
+    /// The clock reads a time before the configured epoch
+    ClockBeforeEpoch { now: u64, epoch: u64 },
+    /// The time since the epoch no longer fits in the timestamp bits
+    TimestampOverflow { timestamp: u64, max: u64 },
+    /// The bit layout cannot describe a valid ID
+    InvalidLayout(&'static str),
+    /// The epoch could not be parsed or lies before 1970
+    InvalidEpoch(&'static str),
+    /// The clock reads earlier than the last issued ID and the rollback
+    /// policy refused to issue another
+    ClockMovedBackwards { last: u64, now: u64 },
+    /// Issuing an ID would have to wait until the clock reads past `until`
+    WouldBlock { until: u64 },
+    /// The clock did not move past `until` before the deadline
+    Timeout { until: u64 },
+    /// The lease on the generator's worker id expired or was taken over
+    LeaseLost { datacenter_id: u64, machine_id: u64 },
+    /// The high-water state file could not be read or written
+    StateFile(String),
+}
+
+impl fmt::Display for SnowflakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnowflakeError::DatacenterIdOutOfRange { id, max } => {
+                write!(f, "datacenter_id {} out of range (max {})", id, max)
+            }
+            SnowflakeError::MachineIdOutOfRange { id, max } => {
+                write!(f, "machine_id {} out of range (max {})", id, max)
+            }
+            SnowflakeError::SequenceOutOfRange { sequence, max } => {
+                write!(f, "sequence {} out of range (max {})", sequence, max)
+            }
+            SnowflakeError::ClockBeforeEpoch { now, epoch } => {
+                write!(
+                    f,
+                    "clock reads {} ms, before the epoch at {} ms",
+                    now, epoch
+                )
+            }
+            SnowflakeError::TimestampOverflow { timestamp, max } => {
+                write!(
+                    f,
+                    "timestamp {} ms past the epoch overflows the layout (max {})",
+                    timestamp, max
+                )
+            }
+            SnowflakeError::InvalidLayout(reason) => write!(f, "invalid bit layout: {}", reason),
+            SnowflakeError::InvalidEpoch(reason) => write!(f, "invalid epoch: {}", reason),
+            SnowflakeError::ClockMovedBackwards { last, now } => write!(
+                f,
+                "clock moved backwards by {} ms (last id at {} ms, clock reads {} ms)",
+                last - now,
+                last,
+                now
+            ),
+            SnowflakeError::WouldBlock { until } => {
+                write!(f, "would block until the clock passes {} ms", until)
+            }
+            SnowflakeError::Timeout { until } => {
+                write!(f, "timed out waiting for the clock to pass {} ms", until)
+            }
+            SnowflakeError::LeaseLost {
+                datacenter_id,
+                machine_id,
+            } => write!(
+                f,
+                "lease on datacenter_id {} machine_id {} was lost",
+                datacenter_id, machine_id
+            ),
+            SnowflakeError::StateFile(reason) => write!(f, "state file {}", reason),
+        }
+    }
+}
+
+impl Error for SnowflakeError {}
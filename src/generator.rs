@@ -0,0 +1,764 @@
+//! The Snowflake ID generator.
+
+use std::ops::Range;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::batch::IdBatch;
+use crate::builder::SnowflakeBuilder;
+use crate::clock::{Clock, SystemClock, wait_next_millis, wait_next_millis_until};
+use crate::decode::DecodedId;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::id::SnowflakeId;
+use crate::layout::BitLayout;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::persist::HighWaterMark;
+use crate::rollback::{RollbackEvent, RollbackListener, RollbackPolicy};
+
+/// How long an ID request may wait for the clock
+#[derive(Debug, Clone, Copy)]
+enum WaitLimit {
+    Forever,
+    Never,
+    Until(Instant),
+}
+
+/// Snowflake ID generator
+///
+/// The last issued timestamp and sequence number are packed into a single
+/// atomic word (`timestamp << sequence_bits | sequence`), so every ID is
+/// claimed by exactly one successful compare-and-swap.
+///
+/// Time is read through the [`Clock`] `C`, the system clock by default.
+pub struct Snowflake<C = SystemClock> {
+    datacenter_id: u64,
+    machine_id: u64,
+    layout: BitLayout,
+    epoch: Epoch,
+    clock: C,
+    rollback_policy: RollbackPolicy,
+    on_rollback: RollbackListener,
+    state: AtomicU64,
+    /// Whether the clock was last seen behind the issued timestamps, so a
+    /// rollback is reported once rather than for every ID issued during it
+    behind: AtomicBool,
+    high_water: Option<HighWaterMark>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<Metrics>,
+    /// One past the last millisecond whose sequence exhaustion was recorded,
+    /// so retries and contending callers count it once
+    #[cfg(feature = "metrics")]
+    exhausted: AtomicU64,
+}
+
+impl Snowflake {
+    /// Create a new Snowflake generator
+    ///
+    /// # Panics
+    ///
+    /// Panics if `datacenter_id` or `machine_id` is out of range. Use
+    /// [`Snowflake::builder`] to handle invalid ids as errors instead.
+    pub fn new(datacenter_id: u64, machine_id: u64) -> Self {
+        match Snowflake::builder()
+            .datacenter_id(datacenter_id)
+            .machine_id(machine_id)
+            .build()
+        {
+            Ok(generator) => generator,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Start building a generator with validated configuration
+    pub fn builder() -> SnowflakeBuilder {
+        SnowflakeBuilder::new()
+    }
+
+    /// Decode an ID back into its components
+    pub fn decode(id: SnowflakeId) -> DecodedId {
+        crate::decode::decode(id)
+    }
+}
+
+impl<C: Clock> Snowflake<C> {
+    /// Create a generator from a builder whose ids were already validated,
+    /// restoring its persisted high-water timestamp if it has a state file
+    pub(crate) fn from_builder(builder: SnowflakeBuilder<C>) -> Result<Self, SnowflakeError> {
+        let mut generator = Snowflake {
+            datacenter_id: builder.datacenter_id,
+            machine_id: builder.machine_id,
+            layout: builder.layout,
+            epoch: builder.epoch,
+            clock: builder.clock,
+            rollback_policy: builder.rollback_policy,
+            on_rollback: builder.on_rollback,
+            state: AtomicU64::new(0),
+            behind: AtomicBool::new(false),
+            high_water: None,
+            #[cfg(feature = "metrics")]
+            metrics: builder.metrics.unwrap_or_default(),
+            #[cfg(feature = "metrics")]
+            exhausted: AtomicU64::new(0),
+        };
+        if let Some(file) = builder.state_file {
+            let until = file
+                .load()?
+                .map_or(0, |mark| mark.saturating_sub(generator.epoch.as_millis()));
+            if until > 0 {
+                generator.advance_past(until - 1 + generator.epoch.as_millis())?;
+            }
+            generator.high_water = Some(HighWaterMark::new(file, until));
+        }
+        Ok(generator)
+    }
+
+    /// Treat every ID up to the Unix timestamp `timestamp` as issued, so
+    /// later IDs carry later timestamps
+    ///
+    /// The generator behaves as if it had just exhausted that millisecond,
+    /// so every rollback policy refuses to go below it.
+    pub(crate) fn advance_past(&self, timestamp: u64) -> Result<(), SnowflakeError> {
+        let Some(timestamp) = timestamp.checked_sub(self.epoch.as_millis()) else {
+            return Ok(());
+        };
+        let last = self.pack(self.check_timestamp(timestamp)?, self.layout.max_sequence());
+        self.state.fetch_max(last, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Unix timestamp of the latest ID issued, or the epoch before the first
+    pub(crate) fn last_timestamp(&self) -> u64 {
+        let (timestamp, _) = self.unpack(self.state.load(Ordering::Acquire));
+        timestamp + self.epoch.as_millis()
+    }
+
+    /// The bit layout of the IDs this generator issues
+    pub fn layout(&self) -> &BitLayout {
+        &self.layout
+    }
+
+    /// The epoch that ID timestamps are counted from
+    pub fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// The clock this generator reads time from
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// The metrics this generator records into
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Generate the next unique ID
+    ///
+    /// Spins when the sequence of the current millisecond is exhausted, and
+    /// on clock rollback as the [`RollbackPolicy`] allows.
+    pub fn next_id(&self) -> Result<SnowflakeId, SnowflakeError> {
+        self.generate(WaitLimit::Forever)
+    }
+
+    /// Generate the next ID without ever waiting for the clock
+    ///
+    /// Where [`next_id`](Self::next_id) would spin, this returns
+    /// [`SnowflakeError::WouldBlock`] with the time to retry after.
+    pub fn try_next_id(&self) -> Result<SnowflakeId, SnowflakeError> {
+        self.generate(WaitLimit::Never)
+    }
+
+    /// Generate the next ID, waiting for the clock for at most `timeout`
+    ///
+    /// Returns [`SnowflakeError::Timeout`] if the clock has not moved far
+    /// enough by then.
+    pub fn next_id_timeout(&self, timeout: Duration) -> Result<SnowflakeId, SnowflakeError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.generate(WaitLimit::Until(deadline)),
+            None => self.generate(WaitLimit::Forever),
+        }
+    }
+
+    /// Reserve `n` IDs at once
+    ///
+    /// Sequence numbers are claimed in contiguous runs, moving on to later
+    /// milliseconds when a run is exhausted. The IDs are ordered after every
+    /// ID issued before the call and before every ID issued after it.
+    pub fn reserve(&self, n: usize) -> Result<IdBatch, SnowflakeError> {
+        let mut ranges = Vec::new();
+        let mut remaining = n as u64;
+        while remaining > 0 {
+            let range = self.claim(remaining, WaitLimit::Forever)?;
+            remaining -= range.end - range.start;
+            ranges.push(range);
+        }
+        Ok(IdBatch::new(ranges))
+    }
+
+    /// Reserve `n` IDs and collect them into a vector
+    pub fn next_ids(&self, n: usize) -> Result<Vec<SnowflakeId>, SnowflakeError> {
+        Ok(self.reserve(n)?.collect())
+    }
+
+    fn generate(&self, limit: WaitLimit) -> Result<SnowflakeId, SnowflakeError> {
+        self.claim(1, limit)
+            .map(|range| SnowflakeId::from_u64(range.start))
+    }
+
+    /// Claim up to `count` consecutive IDs within a single millisecond
+    fn claim(&self, count: u64, limit: WaitLimit) -> Result<Range<u64>, SnowflakeError> {
+        let mut current = self.state.load(Ordering::Acquire);
+        loop {
+            let (last_ts, last_seq) = self.unpack(current);
+            let now = self.since_epoch(self.clock.now_millis())?;
+
+            if now >= last_ts && self.behind.load(Ordering::Relaxed) {
+                self.behind.store(false, Ordering::Relaxed);
+            }
+            let (timestamp, seq) = if now > last_ts {
+                (now, 0)
+            } else if now == last_ts {
+                if last_seq == self.layout.max_sequence() {
+                    // Sequence exhausted in this millisecond, wait for next
+                    #[cfg(feature = "metrics")]
+                    if self.exhausted.fetch_max(last_ts + 1, Ordering::Relaxed) <= last_ts {
+                        self.metrics.record_sequence_exhausted();
+                    }
+                    (self.wait_past(last_ts, limit)?, 0)
+                } else {
+                    (last_ts, last_seq + 1)
+                }
+            } else {
+                if !self.behind.swap(true, Ordering::AcqRel) {
+                    self.on_rollback.notify(&RollbackEvent {
+                        last_timestamp: last_ts + self.epoch.as_millis(),
+                        observed_timestamp: now + self.epoch.as_millis(),
+                        policy: self.rollback_policy,
+                    });
+                    #[cfg(feature = "metrics")]
+                    self.metrics
+                        .record_rollback(Duration::from_millis(last_ts - now));
+                }
+                self.resolve_rollback(last_ts, last_seq, now, limit)?
+            };
+
+            let last = seq + count.min(self.layout.max_sequence() - seq + 1) - 1;
+            match self.state.compare_exchange_weak(
+                current,
+                self.pack(timestamp, last),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    if let Some(high_water) = &self.high_water {
+                        high_water.cover(timestamp, self.epoch.as_millis())?;
+                    }
+                    #[cfg(feature = "metrics")]
+                    self.metrics.record_issued(last - seq + 1);
+                    let first =
+                        self.layout
+                            .compose(timestamp, self.datacenter_id, self.machine_id, seq);
+                    return Ok(first..first + (last - seq) + 1);
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Pick the next `(timestamp, sequence)` when the clock reads `now`,
+    /// earlier than the last issued timestamp
+    fn resolve_rollback(
+        &self,
+        last_ts: u64,
+        last_seq: u64,
+        now: u64,
+        limit: WaitLimit,
+    ) -> Result<(u64, u64), SnowflakeError> {
+        let moved_backwards = SnowflakeError::ClockMovedBackwards {
+            last: last_ts + self.epoch.as_millis(),
+            now: now + self.epoch.as_millis(),
+        };
+        let has_sequence = last_seq < self.layout.max_sequence();
+
+        match self.rollback_policy {
+            RollbackPolicy::Wait { max } if u128::from(last_ts - now) <= max.as_millis() => {
+                Ok((self.wait_past(last_ts, limit)?, 0))
+            }
+            RollbackPolicy::Wait { .. } | RollbackPolicy::Error => Err(moved_backwards),
+            RollbackPolicy::ReuseLastTimestamp if has_sequence => Ok((last_ts, last_seq + 1)),
+            RollbackPolicy::ReuseLastTimestamp => Err(moved_backwards),
+            RollbackPolicy::LogicalClock if has_sequence => Ok((last_ts, last_seq + 1)),
+            RollbackPolicy::LogicalClock => Ok((self.check_timestamp(last_ts + 1)?, 0)),
+        }
+    }
+
+    /// Spin until the clock is past the epoch-relative timestamp `last`
+    fn wait_past(&self, last: u64, limit: WaitLimit) -> Result<u64, SnowflakeError> {
+        let until = last + self.epoch.as_millis();
+        #[cfg(feature = "metrics")]
+        let started = Instant::now();
+        let now = match limit {
+            WaitLimit::Forever => Some(wait_next_millis(&self.clock, until)),
+            WaitLimit::Never => return Err(SnowflakeError::WouldBlock { until }),
+            WaitLimit::Until(deadline) => wait_next_millis_until(&self.clock, until, deadline),
+        };
+        #[cfg(feature = "metrics")]
+        self.metrics.record_wait(started.elapsed());
+        self.since_epoch(now.ok_or(SnowflakeError::Timeout { until })?)
+    }
+
+    /// Convert a Unix timestamp into milliseconds since the configured epoch
+    fn since_epoch(&self, now: u64) -> Result<u64, SnowflakeError> {
+        let epoch = self.epoch.as_millis();
+        let timestamp = now
+            .checked_sub(epoch)
+            .ok_or(SnowflakeError::ClockBeforeEpoch { now, epoch })?;
+        self.check_timestamp(timestamp)
+    }
+
+    /// Check that an epoch-relative timestamp fits the layout
+    fn check_timestamp(&self, timestamp: u64) -> Result<u64, SnowflakeError> {
+        if timestamp > self.layout.max_timestamp() {
+            return Err(SnowflakeError::TimestampOverflow {
+                timestamp,
+                max: self.layout.max_timestamp(),
+            });
+        }
+        Ok(timestamp)
+    }
+
+    /// Pack an epoch-relative timestamp and a sequence number into one word
+    fn pack(&self, timestamp: u64, sequence: u64) -> u64 {
+        (timestamp << self.layout.sequence_bits()) | sequence
+    }
+
+    /// Split a packed state word into `(timestamp, sequence)`
+    fn unpack(&self, state: u64) -> (u64, u64) {
+        (
+            state >> self.layout.sequence_bits(),
+            state & self.layout.max_sequence(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::layout::{CUSTOM_EPOCH, MAX_MACHINE, MAX_SEQUENCE, MAX_TIMESTAMP};
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_snowflake_id_generation() {
+        let generator = Snowflake::new(1, 1);
+        let id1 = generator.next_id().unwrap();
+        let id2 = generator.next_id().unwrap();
+        assert!(id2 > id1, "IDs should be monotonically increasing");
+    }
+
+    #[test]
+    fn test_unique_and_ordered() {
+        let generator = Snowflake::new(1, 1);
+        let mut last = SnowflakeId::default();
+        for _ in 0..1000 {
+            let id = generator.next_id().unwrap();
+            assert!(id > last, "IDs must be ordered");
+            last = id;
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let generator = Snowflake::new(2, 3);
+        let id = generator.next_id().unwrap();
+        let decoded = Snowflake::decode(id);
+
+        assert_eq!(decoded.datacenter, 2);
+        assert_eq!(decoded.machine, 3);
+        assert!(decoded.timestamp >= CUSTOM_EPOCH);
+    }
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let generator = Snowflake::new(0, 0);
+        assert_eq!(
+            generator.unpack(generator.pack(123_456, MAX_SEQUENCE)),
+            (123_456, MAX_SEQUENCE)
+        );
+        assert_eq!(generator.unpack(generator.pack(0, 0)), (0, 0));
+    }
+
+    #[test]
+    fn test_no_duplicates_under_contention() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 20_000;
+
+        let generator = Arc::new(Snowflake::new(1, 1));
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                thread::spawn(move || {
+                    let mut ids = Vec::with_capacity(PER_THREAD);
+                    let mut last = SnowflakeId::default();
+                    for _ in 0..PER_THREAD {
+                        let id = generator.next_id().unwrap();
+                        assert!(id > last, "IDs must be ordered within a thread");
+                        last = id;
+                        ids.push(id);
+                    }
+                    ids
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::with_capacity(THREADS * PER_THREAD);
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(seen.insert(id), "duplicate id {}", id);
+            }
+        }
+        assert_eq!(seen.len(), THREADS * PER_THREAD);
+    }
+
+    #[test]
+    #[should_panic(expected = "machine_id 32 out of range")]
+    fn test_machine_out_of_range() {
+        Snowflake::new(0, MAX_MACHINE + 1);
+    }
+
+    #[test]
+    fn test_since_epoch_bounds() {
+        let generator = Snowflake::new(0, 0);
+        assert_eq!(
+            generator.since_epoch(CUSTOM_EPOCH - 1),
+            Err(SnowflakeError::ClockBeforeEpoch {
+                now: CUSTOM_EPOCH - 1,
+                epoch: CUSTOM_EPOCH
+            })
+        );
+        assert_eq!(
+            generator.since_epoch(CUSTOM_EPOCH + MAX_TIMESTAMP),
+            Ok(MAX_TIMESTAMP)
+        );
+        assert_eq!(
+            generator.since_epoch(CUSTOM_EPOCH + MAX_TIMESTAMP + 1),
+            Err(SnowflakeError::TimestampOverflow {
+                timestamp: MAX_TIMESTAMP + 1,
+                max: MAX_TIMESTAMP
+            })
+        );
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let layout = BitLayout::new(41, 0, 10, 12).unwrap();
+        let generator = Snowflake::builder()
+            .layout(layout)
+            .machine_id(1000)
+            .build()
+            .unwrap();
+        let id = generator.next_id().unwrap();
+        let decoded = crate::decode::decode_with(id, &layout, generator.epoch());
+        assert_eq!((decoded.datacenter, decoded.machine), (0, 1000));
+    }
+
+    #[test]
+    fn test_custom_epoch() {
+        let epoch: Epoch = "2024-01-01T00:00:00Z".parse().unwrap();
+        let generator = Snowflake::builder().epoch(epoch).build().unwrap();
+        let id = generator.next_id().unwrap();
+        let decoded = crate::decode::decode_with(id, generator.layout(), epoch);
+        assert!(decoded.timestamp >= epoch.as_millis());
+        assert!(id < Snowflake::new(0, 0).next_id().unwrap());
+    }
+
+    #[test]
+    fn test_rejects_clock_before_epoch() {
+        let epoch = Epoch::from_millis(u64::MAX / 2);
+        let generator = Snowflake::builder().epoch(epoch).build().unwrap();
+        assert!(matches!(
+            generator.next_id(),
+            Err(SnowflakeError::ClockBeforeEpoch { epoch: e, .. }) if e == epoch.as_millis()
+        ));
+    }
+
+    /// A generator issuing four IDs per millisecond from a manual clock
+    fn manual_generator(clock: &Arc<ManualClock>) -> Snowflake<Arc<ManualClock>> {
+        manual_builder(clock).build().unwrap()
+    }
+
+    fn manual_builder(clock: &Arc<ManualClock>) -> SnowflakeBuilder<Arc<ManualClock>> {
+        Snowflake::builder()
+            .layout(BitLayout::new(61, 0, 0, 2).unwrap())
+            .epoch(Epoch::UNIX)
+            .clock(Arc::clone(clock))
+    }
+
+    /// Set `clock` to `millis` once the caller has had time to block on it
+    fn set_later(clock: &Arc<ManualClock>, millis: u64) -> thread::JoinHandle<()> {
+        let clock = Arc::clone(clock);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            clock.set(millis);
+        })
+    }
+
+    #[test]
+    fn test_manual_clock_sequence_exhaustion() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        let ids: Vec<u64> = (0..4)
+            .map(|_| generator.next_id().unwrap().as_u64())
+            .collect();
+        assert_eq!(ids, [4000, 4001, 4002, 4003]);
+
+        // The fifth ID has to wait for the next millisecond
+        let advancer = set_later(&clock, 1001);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4004);
+        advancer.join().unwrap();
+    }
+
+    #[test]
+    fn test_manual_clock_rollback_waits() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4000);
+
+        clock.rewind(Duration::from_millis(10));
+        let advancer = set_later(&clock, 1001);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4004);
+        advancer.join().unwrap();
+    }
+
+    #[test]
+    fn test_rollback_error_policy_reports_event() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let generator = {
+            let events = Arc::clone(&events);
+            manual_builder(&clock)
+                .rollback_policy(RollbackPolicy::Error)
+                .on_rollback(move |event| events.lock().unwrap().push(*event))
+                .build()
+                .unwrap()
+        };
+        generator.next_id().unwrap();
+
+        clock.set(990);
+        assert_eq!(
+            generator.next_id(),
+            Err(SnowflakeError::ClockMovedBackwards {
+                last: 1000,
+                now: 990
+            })
+        );
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].magnitude(), Duration::from_millis(10));
+        assert_eq!(events[0].policy, RollbackPolicy::Error);
+    }
+
+    #[test]
+    fn test_rollback_bounded_wait() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_builder(&clock)
+            .rollback_policy(RollbackPolicy::Wait {
+                max: Duration::from_millis(5),
+            })
+            .build()
+            .unwrap();
+        generator.next_id().unwrap();
+
+        clock.set(990);
+        assert!(matches!(
+            generator.next_id(),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        clock.set(997);
+        let advancer = set_later(&clock, 1001);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4004);
+        advancer.join().unwrap();
+    }
+
+    #[test]
+    fn test_rollback_reuse_last_timestamp() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_builder(&clock)
+            .rollback_policy(RollbackPolicy::ReuseLastTimestamp)
+            .build()
+            .unwrap();
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4000);
+
+        clock.set(900);
+        let ids: Vec<u64> = (0..3)
+            .map(|_| generator.next_id().unwrap().as_u64())
+            .collect();
+        assert_eq!(ids, [4001, 4002, 4003]);
+        assert!(generator.next_id().is_err());
+    }
+
+    #[test]
+    fn test_rollback_reported_once_per_event() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let generator = {
+            let events = Arc::clone(&events);
+            manual_builder(&clock)
+                .rollback_policy(RollbackPolicy::LogicalClock)
+                .on_rollback(move |event| events.lock().unwrap().push(*event))
+                .build()
+                .unwrap()
+        };
+        generator.next_id().unwrap();
+
+        clock.set(900);
+        for _ in 0..10 {
+            generator.next_id().unwrap();
+        }
+        clock.set(901);
+        generator.next_id().unwrap();
+        assert_eq!(events.lock().unwrap().len(), 1);
+
+        // Caught up, then behind again: a second rollback
+        clock.set(2000);
+        generator.next_id().unwrap();
+        clock.set(1500);
+        generator.next_id().unwrap();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].magnitude(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_rollback_logical_clock() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_builder(&clock)
+            .rollback_policy(RollbackPolicy::LogicalClock)
+            .build()
+            .unwrap();
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4000);
+
+        clock.set(900);
+        let ids: Vec<u64> = (0..6)
+            .map(|_| generator.next_id().unwrap().as_u64())
+            .collect();
+        assert_eq!(ids, [4001, 4002, 4003, 4004, 4005, 4006]);
+    }
+
+    #[test]
+    fn test_try_next_id_would_block() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        for _ in 0..4 {
+            generator.try_next_id().unwrap();
+        }
+        assert_eq!(
+            generator.try_next_id(),
+            Err(SnowflakeError::WouldBlock { until: 1000 })
+        );
+
+        clock.set(990);
+        assert_eq!(
+            generator.try_next_id(),
+            Err(SnowflakeError::WouldBlock { until: 1000 })
+        );
+
+        clock.set(1001);
+        assert_eq!(generator.try_next_id().unwrap().as_u64(), 4004);
+    }
+
+    #[test]
+    fn test_next_id_timeout() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        for _ in 0..4 {
+            generator.next_id().unwrap();
+        }
+
+        let started = Instant::now();
+        assert_eq!(
+            generator.next_id_timeout(Duration::from_millis(20)),
+            Err(SnowflakeError::Timeout { until: 1000 })
+        );
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let advancer = set_later(&clock, 1001);
+        assert_eq!(
+            generator
+                .next_id_timeout(Duration::from_secs(10))
+                .unwrap()
+                .as_u64(),
+            4004
+        );
+        advancer.join().unwrap();
+    }
+
+    #[test]
+    fn test_reserve_spans_milliseconds() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4000);
+
+        let advancer = set_later(&clock, 1001);
+        let batch = generator.reserve(5).unwrap();
+        assert_eq!(
+            batch.ranges().cloned().collect::<Vec<_>>(),
+            [4001..4004, 4004..4006]
+        );
+        assert_eq!(
+            batch.map(SnowflakeId::as_u64).collect::<Vec<_>>(),
+            [4001, 4002, 4003, 4004, 4005]
+        );
+        advancer.join().unwrap();
+
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4006);
+    }
+
+    #[test]
+    fn test_huge_claim_stops_at_the_last_sequence() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let generator = manual_generator(&clock);
+        assert_eq!(generator.next_id().unwrap().as_u64(), 4000);
+
+        // What `reserve(usize::MAX)` asks for first
+        let range = generator
+            .claim(usize::MAX as u64, WaitLimit::Never)
+            .unwrap();
+        assert_eq!(range, 4001..4004);
+        assert!(matches!(
+            generator.claim(u64::MAX, WaitLimit::Never),
+            Err(SnowflakeError::WouldBlock { .. })
+        ));
+    }
+
+    #[test]
+    fn test_next_ids_interleaved_with_next_id() {
+        let generator = Arc::new(Snowflake::new(1, 1));
+        let batcher = {
+            let generator = Arc::clone(&generator);
+            thread::spawn(move || {
+                (0..50)
+                    .flat_map(|_| generator.next_ids(1000).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let singles: Vec<SnowflakeId> = (0..20_000).map(|_| generator.next_id().unwrap()).collect();
+        let batched = batcher.join().unwrap();
+
+        assert!(batched.windows(2).all(|pair| pair[0] < pair[1]));
+        let mut seen = HashSet::new();
+        for id in singles.into_iter().chain(batched) {
+            assert!(seen.insert(id), "duplicate id {}", id);
+        }
+        assert_eq!(generator.next_ids(0).unwrap(), []);
+    }
+}
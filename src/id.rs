@@ -0,0 +1,188 @@
+//! The [`SnowflakeId`] type.
+
+use std::error::Error;
+use std::fmt;
+use std::num::TryFromIntError;
+use std::str::FromStr;
+
+use crate::decode::decode;
+
+/// A 64-bit Snowflake ID
+///
+/// Accessors read the ID with the default 41/5/5/12 layout and the Twitter
+/// epoch. Use [`decode_with`](crate::decode_with) for IDs issued with any
+/// other configuration.
+///
+/// Conversions to and from plain integers are explicit:
+///
+/// ```
+/// use id_gnrt_rust_impl::SnowflakeId;
+///
+/// let id = SnowflakeId::from_u64(1_541_815_603_604_623_360);
+/// assert_eq!((id.datacenter(), id.machine()), (1, 1));
+/// assert_eq!(u64::from(id), 1_541_815_603_604_623_360);
+/// assert_eq!(i64::try_from(id)?, 1_541_815_603_604_623_360);
+/// assert_eq!("1541815603604623360".parse::<SnowflakeId>()?, id);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnowflakeId(u64);
+
+impl SnowflakeId {
+    /// Wrap a raw 64-bit ID
+    pub const fn from_u64(id: u64) -> Self {
+        SnowflakeId(id)
+    }
+
+    /// The raw 64-bit ID
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Creation time in milliseconds since the Unix epoch
+    pub fn timestamp(self) -> u64 {
+        decode(self).timestamp
+    }
+
+    /// Datacenter id of the generator that issued this ID
+    pub fn datacenter(self) -> u64 {
+        decode(self).datacenter
+    }
+
+    /// Machine id of the generator that issued this ID
+    pub fn machine(self) -> u64 {
+        decode(self).machine
+    }
+
+    /// Sequence number within its millisecond
+    pub fn sequence(self) -> u64 {
+        decode(self).sequence
+    }
+}
+
+impl From<u64> for SnowflakeId {
+    fn from(id: u64) -> Self {
+        SnowflakeId(id)
+    }
+}
+
+impl From<SnowflakeId> for u64 {
+    fn from(id: SnowflakeId) -> Self {
+        id.0
+    }
+}
+
+impl TryFrom<i64> for SnowflakeId {
+    type Error = TryFromIntError;
+
+    /// Fails for negative values
+    fn try_from(id: i64) -> Result<Self, Self::Error> {
+        u64::try_from(id).map(SnowflakeId)
+    }
+}
+
+impl TryFrom<SnowflakeId> for i64 {
+    type Error = TryFromIntError;
+
+    /// Fails if the sign bit is set, which a generator never does
+    fn try_from(id: SnowflakeId) -> Result<Self, Self::Error> {
+        i64::try_from(id.0)
+    }
+}
+
+impl fmt::Display for SnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for SnowflakeId {
+    type Err = ParseIdError;
+
+    /// Parse the decimal form produced by `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseIdError::Empty);
+        }
+        if let Some((index, ch)) = s.char_indices().find(|(_, ch)| !ch.is_ascii_digit()) {
+            return Err(ParseIdError::InvalidDigit { ch, index });
+        }
+        s.parse()
+            .map(SnowflakeId)
+            .map_err(|_| ParseIdError::Overflow)
+    }
+}
+
+/// Error returned when text cannot be parsed as a [`SnowflakeId`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseIdError {
+    /// The input was empty
+    Empty,
+    /// The input contains a character outside the encoding's alphabet
+    InvalidDigit { ch: char, index: usize },
+    /// The value does not fit in 64 bits
+    Overflow,
+    /// A fixed-width encoding has the wrong number of characters
+    InvalidLength { len: usize, expected: usize },
+}
+
+impl fmt::Display for ParseIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseIdError::Empty => f.write_str("cannot parse an id from an empty string"),
+            ParseIdError::InvalidDigit { ch, index } => {
+                write!(f, "invalid character {:?} at position {}", ch, index)
+            }
+            ParseIdError::Overflow => f.write_str("id does not fit in 64 bits"),
+            ParseIdError::InvalidLength { len, expected } => {
+                write!(f, "expected {} characters, found {}", expected, len)
+            }
+        }
+    }
+}
+
+impl Error for ParseIdError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{BitLayout, CUSTOM_EPOCH};
+
+    #[test]
+    fn test_accessors() {
+        let id = SnowflakeId::from_u64(BitLayout::DEFAULT.compose(42, 3, 7, 99));
+        assert_eq!(id.timestamp(), CUSTOM_EPOCH + 42);
+        assert_eq!(id.datacenter(), 3);
+        assert_eq!(id.machine(), 7);
+        assert_eq!(id.sequence(), 99);
+    }
+
+    #[test]
+    fn test_signed_conversions() {
+        assert!(SnowflakeId::try_from(-1i64).is_err());
+        assert_eq!(
+            SnowflakeId::try_from(i64::MAX).unwrap().as_u64(),
+            i64::MAX as u64
+        );
+        assert!(i64::try_from(SnowflakeId::from_u64(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        let id = SnowflakeId::from_u64(u64::MAX);
+        assert_eq!(id.to_string().parse(), Ok(id));
+        assert_eq!("".parse::<SnowflakeId>(), Err(ParseIdError::Empty));
+        assert_eq!(
+            "12a4".parse::<SnowflakeId>(),
+            Err(ParseIdError::InvalidDigit { ch: 'a', index: 2 })
+        );
+        assert_eq!(
+            "+1".parse::<SnowflakeId>(),
+            Err(ParseIdError::InvalidDigit { ch: '+', index: 0 })
+        );
+        assert_eq!(
+            "18446744073709551616".parse::<SnowflakeId>(),
+            Err(ParseIdError::Overflow)
+        );
+    }
+}
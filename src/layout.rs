@@ -0,0 +1,251 @@
+//! Bit layout of a Snowflake ID.
+//!
+//! From most to least significant bit an ID is made of an unused sign bit,
+//! the timestamp, the datacenter id, the machine id and the sequence number.
+//! The constants below describe the classic 41/5/5/12 split; [`BitLayout`]
+//! describes any other split at runtime.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::SnowflakeError;
+
+/// Number of bits allocated to each part of the Snowflake ID
+pub const SIGN_BITS: u64 = 1;
+pub const TIMESTAMP_BITS: u64 = 41;
+pub const DATACENTER_BITS: u64 = 5;
+pub const MACHINE_BITS: u64 = 5;
+pub const SEQUENCE_BITS: u64 = 12;
+
+/// Bit shifts
+pub const MACHINE_SHIFT: u64 = SEQUENCE_BITS;
+pub const DATACENTER_SHIFT: u64 = MACHINE_SHIFT + MACHINE_BITS;
+pub const TIMESTAMP_SHIFT: u64 = DATACENTER_SHIFT + DATACENTER_BITS;
+
+/// Max values
+pub const MAX_TIMESTAMP: u64 = (1 << TIMESTAMP_BITS) - 1;
+pub const MAX_DATACENTER: u64 = (1 << DATACENTER_BITS) - 1;
+pub const MAX_MACHINE: u64 = (1 << MACHINE_BITS) - 1;
+pub const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+
+/// Twitter custom epoch: Nov 04 2010 01:42:54 UTC
+pub const CUSTOM_EPOCH: u64 = 1288834974657;
+
+/// Widths of the timestamp, datacenter, machine and sequence fields
+///
+/// The four widths always sum to 63 bits, leaving the sign bit clear.
+/// Datacenter and machine fields may be zero bits wide; timestamp and
+/// sequence fields need at least one bit.
+///
+/// ```
+/// use id_gnrt_rust_impl::layout::BitLayout;
+///
+/// // 10 machine bits and no datacenter
+/// let layout = BitLayout::new(41, 0, 10, 12)?;
+/// assert_eq!(layout.max_machine(), 1023);
+/// assert_eq!(layout.max_datacenter(), 0);
+/// # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitLayout {
+    timestamp_bits: u64,
+    datacenter_bits: u64,
+    machine_bits: u64,
+    sequence_bits: u64,
+}
+
+impl BitLayout {
+    /// The classic 41/5/5/12 layout
+    pub const DEFAULT: BitLayout = BitLayout {
+        timestamp_bits: TIMESTAMP_BITS,
+        datacenter_bits: DATACENTER_BITS,
+        machine_bits: MACHINE_BITS,
+        sequence_bits: SEQUENCE_BITS,
+    };
+
+    /// Create a layout, checking that the widths fill the 63 usable bits
+    pub const fn new(
+        timestamp_bits: u64,
+        datacenter_bits: u64,
+        machine_bits: u64,
+        sequence_bits: u64,
+    ) -> Result<Self, SnowflakeError> {
+        if timestamp_bits == 0 {
+            return Err(SnowflakeError::InvalidLayout(
+                "timestamp needs at least one bit",
+            ));
+        }
+        if sequence_bits == 0 {
+            return Err(SnowflakeError::InvalidLayout(
+                "sequence needs at least one bit",
+            ));
+        }
+        let total = timestamp_bits
+            .saturating_add(datacenter_bits)
+            .saturating_add(machine_bits)
+            .saturating_add(sequence_bits);
+        if total != 64 - SIGN_BITS {
+            return Err(SnowflakeError::InvalidLayout(
+                "field widths must sum to 63 bits",
+            ));
+        }
+
+        Ok(BitLayout {
+            timestamp_bits,
+            datacenter_bits,
+            machine_bits,
+            sequence_bits,
+        })
+    }
+
+    /// Width of the timestamp field
+    pub const fn timestamp_bits(&self) -> u64 {
+        self.timestamp_bits
+    }
+
+    /// Width of the datacenter field
+    pub const fn datacenter_bits(&self) -> u64 {
+        self.datacenter_bits
+    }
+
+    /// Width of the machine field
+    pub const fn machine_bits(&self) -> u64 {
+        self.machine_bits
+    }
+
+    /// Width of the sequence field
+    pub const fn sequence_bits(&self) -> u64 {
+        self.sequence_bits
+    }
+
+    /// Bit offset of the machine field
+    pub const fn machine_shift(&self) -> u64 {
+        self.sequence_bits
+    }
+
+    /// Bit offset of the datacenter field
+    pub const fn datacenter_shift(&self) -> u64 {
+        self.machine_shift() + self.machine_bits
+    }
+
+    /// Bit offset of the timestamp field
+    pub const fn timestamp_shift(&self) -> u64 {
+        self.datacenter_shift() + self.datacenter_bits
+    }
+
+    /// Largest timestamp, in milliseconds since the epoch
+    pub const fn max_timestamp(&self) -> u64 {
+        (1 << self.timestamp_bits) - 1
+    }
+
+    /// Largest datacenter id
+    pub const fn max_datacenter(&self) -> u64 {
+        (1 << self.datacenter_bits) - 1
+    }
+
+    /// Largest machine id
+    pub const fn max_machine(&self) -> u64 {
+        (1 << self.machine_bits) - 1
+    }
+
+    /// Largest sequence number within one millisecond
+    pub const fn max_sequence(&self) -> u64 {
+        (1 << self.sequence_bits) - 1
+    }
+
+    /// Assemble an ID from fields already known to fit the layout
+    pub const fn compose(
+        &self,
+        timestamp: u64,
+        datacenter: u64,
+        machine: u64,
+        sequence: u64,
+    ) -> u64 {
+        (timestamp << self.timestamp_shift())
+            | (datacenter << self.datacenter_shift())
+            | (machine << self.machine_shift())
+            | sequence
+    }
+}
+
+impl Default for BitLayout {
+    fn default() -> Self {
+        BitLayout::DEFAULT
+    }
+}
+
+impl fmt::Display for BitLayout {
+    /// Formats as `timestamp,datacenter,machine,sequence` widths
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.timestamp_bits, self.datacenter_bits, self.machine_bits, self.sequence_bits
+        )
+    }
+}
+
+impl FromStr for BitLayout {
+    type Err = SnowflakeError;
+
+    /// Parses `timestamp,datacenter,machine,sequence` widths, e.g. `41,5,5,12`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = SnowflakeError::InvalidLayout("expected four comma-separated bit widths");
+        let widths = s
+            .split(',')
+            .map(|width| width.trim().parse::<u64>().map_err(|_| invalid.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match widths[..] {
+            [timestamp, datacenter, machine, sequence] => {
+                BitLayout::new(timestamp, datacenter, machine, sequence)
+            }
+            _ => Err(invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_fills_63_bits() {
+        assert_eq!(
+            SIGN_BITS + TIMESTAMP_BITS + DATACENTER_BITS + MACHINE_BITS + SEQUENCE_BITS,
+            64
+        );
+        assert_eq!(TIMESTAMP_SHIFT + TIMESTAMP_BITS, 63);
+    }
+
+    #[test]
+    fn test_default_layout_matches_constants() {
+        let layout = BitLayout::DEFAULT;
+        assert_eq!(BitLayout::new(41, 5, 5, 12), Ok(layout));
+        assert_eq!(layout.machine_shift(), MACHINE_SHIFT);
+        assert_eq!(layout.datacenter_shift(), DATACENTER_SHIFT);
+        assert_eq!(layout.timestamp_shift(), TIMESTAMP_SHIFT);
+        assert_eq!(layout.max_timestamp(), MAX_TIMESTAMP);
+        assert_eq!(layout.max_datacenter(), MAX_DATACENTER);
+        assert_eq!(layout.max_machine(), MAX_MACHINE);
+        assert_eq!(layout.max_sequence(), MAX_SEQUENCE);
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        assert!(BitLayout::new(41, 5, 5, 13).is_err());
+        assert!(BitLayout::new(0, 21, 30, 12).is_err());
+        assert!(BitLayout::new(51, 0, 12, 0).is_err());
+        assert!(BitLayout::new(u64::MAX, 1, 1, 1).is_err());
+        assert!(BitLayout::new(41, 0, 10, 12).is_ok());
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!("41,5,5,12".parse(), Ok(BitLayout::DEFAULT));
+        assert_eq!(BitLayout::DEFAULT.to_string(), "41,5,5,12");
+        assert_eq!(" 41, 0,10 ,12".parse(), BitLayout::new(41, 0, 10, 12));
+        assert!("41,5,5".parse::<BitLayout>().is_err());
+        assert!("41,5,5,x".parse::<BitLayout>().is_err());
+        assert!("41,5,5,13".parse::<BitLayout>().is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! A fast, thread-safe implementation of the Snowflake ID generator algorithm.
+//!
+//! ```
+//! use id_gnrt_rust_impl::Snowflake;
+//!
+//! let generator = Snowflake::builder().datacenter_id(1).machine_id(1).build()?;
+//! let id = generator.next_id()?;
+//! let decoded = Snowflake::decode(id);
+//! assert_eq!((decoded.datacenter, decoded.machine), (1, 1));
+//! # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+//! ```
+
+#[cfg(feature = "async")]
+mod asynchronous;
+pub mod batch;
+pub mod builder;
+pub mod clock;
+pub mod decode;
+pub mod encoding;
+pub mod epoch;
+pub mod error;
+pub mod generator;
+pub mod id;
+pub mod layout;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod node;
+pub mod persist;
+pub mod registry;
+pub mod rollback;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(test)]
+mod testing;
+pub mod worker;
+
+pub use batch::IdBatch;
+pub use builder::SnowflakeBuilder;
+pub use clock::{Clock, ManualClock, MonotonicClock, SystemClock};
+pub use decode::{DecodedId, decode, decode_with};
+pub use epoch::Epoch;
+pub use error::SnowflakeError;
+pub use generator::Snowflake;
+pub use id::{ParseIdError, SnowflakeId};
+pub use layout::BitLayout;
+pub use rollback::{RollbackEvent, RollbackPolicy};
@@ -0,0 +1,311 @@
+//! Prometheus metrics for the generator.
+//!
+//! Available with the `metrics` feature. Every [`Snowflake`](crate::Snowflake)
+//! records into a [`Metrics`], its own by default or one shared through
+//! [`SnowflakeBuilder::metrics`](crate::SnowflakeBuilder::metrics), and
+//! [`Metrics::render`] produces the Prometheus text exposition format:
+//!
+//! | Metric                                  | Type      | Meaning                                      |
+//! |-----------------------------------------|-----------|----------------------------------------------|
+//! | `snowflake_ids_issued_total`            | counter   | IDs handed out, including reserved batches   |
+//! | `snowflake_sequence_exhausted_total`    | counter   | times a millisecond ran out of sequence numbers |
+//! | `snowflake_clock_rollbacks_total`       | counter   | times the clock was seen moving backwards    |
+//! | `snowflake_clock_rollback_seconds`      | histogram | how far the clock moved backwards            |
+//! | `snowflake_wait_seconds`                | histogram | time spent spinning in `wait_next_millis`    |
+//!
+//! The HTTP server mounts the output at `GET /metrics` and the RESP server
+//! returns it from the `METRICS` command.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The `Content-Type` of [`Metrics::render`]'s output
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds of the rollback magnitude buckets
+const ROLLBACK_BUCKETS: &[Duration] = &[
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+];
+
+/// Upper bounds of the wait time buckets
+const WAIT_BUCKETS: &[Duration] = &[
+    Duration::from_micros(10),
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+];
+
+/// Counters and histograms describing a generator's behaviour
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use id_gnrt_rust_impl::Snowflake;
+/// use id_gnrt_rust_impl::metrics::Metrics;
+///
+/// let metrics = Arc::new(Metrics::new());
+/// let generator = Snowflake::builder().metrics(Arc::clone(&metrics)).build()?;
+/// generator.next_ids(3)?;
+/// assert_eq!(metrics.ids_issued(), 3);
+/// assert!(metrics.render().contains("snowflake_ids_issued_total 3\n"));
+/// # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+/// ```
+#[derive(Debug)]
+pub struct Metrics {
+    ids_issued: AtomicU64,
+    sequence_exhausted: AtomicU64,
+    rollbacks: AtomicU64,
+    rollback_magnitude: Histogram,
+    wait_time: Histogram,
+}
+
+impl Metrics {
+    /// All counters and histograms at zero
+    pub fn new() -> Self {
+        Metrics {
+            ids_issued: AtomicU64::new(0),
+            sequence_exhausted: AtomicU64::new(0),
+            rollbacks: AtomicU64::new(0),
+            rollback_magnitude: Histogram::new(ROLLBACK_BUCKETS),
+            wait_time: Histogram::new(WAIT_BUCKETS),
+        }
+    }
+
+    /// Number of IDs issued
+    pub fn ids_issued(&self) -> u64 {
+        self.ids_issued.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the sequence of a millisecond was exhausted
+    pub fn sequence_exhausted(&self) -> u64 {
+        self.sequence_exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Number of clock rollbacks observed
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks.load(Ordering::Relaxed)
+    }
+
+    /// How far the clock moved backwards in each rollback
+    pub fn rollback_magnitude(&self) -> &Histogram {
+        &self.rollback_magnitude
+    }
+
+    /// Time spent waiting for the clock to reach the next millisecond
+    pub fn wait_time(&self) -> &Histogram {
+        &self.wait_time
+    }
+
+    /// Render every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        write_counter(
+            &mut out,
+            "snowflake_ids_issued_total",
+            "IDs issued by the generator.",
+            self.ids_issued(),
+        );
+        write_counter(
+            &mut out,
+            "snowflake_sequence_exhausted_total",
+            "Times the sequence of a millisecond was exhausted.",
+            self.sequence_exhausted(),
+        );
+        write_counter(
+            &mut out,
+            "snowflake_clock_rollbacks_total",
+            "Times the clock was seen moving backwards.",
+            self.rollbacks(),
+        );
+        self.rollback_magnitude.write(
+            &mut out,
+            "snowflake_clock_rollback_seconds",
+            "How far the clock moved backwards.",
+        );
+        self.wait_time.write(
+            &mut out,
+            "snowflake_wait_seconds",
+            "Time spent waiting for the clock to reach the next millisecond.",
+        );
+        out
+    }
+
+    pub(crate) fn record_issued(&self, count: u64) {
+        self.ids_issued.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_sequence_exhausted(&self) {
+        self.sequence_exhausted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_rollback(&self, magnitude: Duration) {
+        self.rollbacks.fetch_add(1, Ordering::Relaxed);
+        self.rollback_magnitude.observe(magnitude);
+    }
+
+    pub(crate) fn record_wait(&self, waited: Duration) {
+        self.wait_time.observe(waited);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// A histogram of durations with fixed bucket bounds
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [Duration],
+    /// Observations per bucket, not cumulative; the last one is `+Inf`
+    buckets: Box<[AtomicU64]>,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [Duration]) -> Self {
+        Histogram {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Sum of all observations
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+
+    fn observe(&self, value: Duration) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            match self.bounds.get(i) {
+                Some(bound) => {
+                    let le = bound.as_secs_f64();
+                    let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative);
+                }
+                None => {
+                    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, cumulative);
+                }
+            }
+        }
+        let _ = writeln!(out, "{}_sum {}", name, self.sum().as_secs_f64());
+        let _ = writeln!(out, "{}_count {}", name, cumulative);
+    }
+}
+
+fn write_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::epoch::Epoch;
+    use crate::generator::Snowflake;
+    use crate::layout::BitLayout;
+    use crate::rollback::RollbackPolicy;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        const BOUNDS: &[Duration] = &[Duration::from_millis(1), Duration::from_millis(10)];
+        let histogram = Histogram::new(BOUNDS);
+        histogram.observe(Duration::from_millis(1));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_secs(1));
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.sum(), Duration::from_millis(1006));
+
+        let mut out = String::new();
+        histogram.write(&mut out, "h", "A histogram.");
+        assert_eq!(
+            out,
+            "# HELP h A histogram.\n\
+             # TYPE h histogram\n\
+             h_bucket{le=\"0.001\"} 1\n\
+             h_bucket{le=\"0.01\"} 2\n\
+             h_bucket{le=\"+Inf\"} 3\n\
+             h_sum 1.006\n\
+             h_count 3\n"
+        );
+    }
+
+    #[test]
+    fn test_generator_records_exhaustion_and_rollback() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let metrics = Arc::new(Metrics::new());
+        let generator = Snowflake::builder()
+            .layout(BitLayout::new(61, 0, 0, 2).unwrap())
+            .epoch(Epoch::UNIX)
+            .clock(Arc::clone(&clock))
+            .rollback_policy(RollbackPolicy::LogicalClock)
+            .metrics(Arc::clone(&metrics))
+            .build()
+            .unwrap();
+
+        generator.next_ids(4).unwrap();
+        for _ in 0..3 {
+            assert!(generator.try_next_id().is_err());
+        }
+        assert_eq!(metrics.sequence_exhausted(), 1);
+
+        clock.set(1001);
+        generator.next_id().unwrap();
+        clock.set(990);
+        for _ in 0..5 {
+            generator.next_id().unwrap();
+        }
+        assert_eq!(metrics.ids_issued(), 10);
+        assert_eq!(metrics.rollbacks(), 1);
+        assert_eq!(
+            metrics.rollback_magnitude().sum(),
+            Duration::from_millis(11)
+        );
+        assert!(
+            metrics
+                .render()
+                .contains("snowflake_clock_rollback_seconds_bucket{le=\"0.05\"} 1\n")
+        );
+    }
+}
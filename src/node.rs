@@ -0,0 +1,345 @@
+//! Datacenter and machine ids derived from the deployment environment.
+//!
+//! Each [`NodeId`] constructor reads one source: environment variables, a
+//! Kubernetes StatefulSet pod ordinal, the low bits of an IPv4 address or a
+//! hash of the hostname. Ids are derived for the [`BitLayout`] the generator
+//! will use: explicit ids that do not fit it are reported as errors, while
+//! the address and hostname constructors keep only the bits it holds. Hand
+//! the result to [`SnowflakeBuilder::node_id`](crate::SnowflakeBuilder::node_id)
+//! together with the same layout.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+
+use crate::layout::BitLayout;
+
+/// Variable read by [`NodeId::from_env`] for the datacenter id
+pub const DATACENTER_ENV: &str = "SNOWFLAKE_DATACENTER_ID";
+/// Variable read by [`NodeId::from_env`] for the machine id
+pub const MACHINE_ENV: &str = "SNOWFLAKE_MACHINE_ID";
+
+/// Errors reported while deriving a node id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeIdError {
+    /// A required environment variable is not set
+    MissingEnv(String),
+    /// An environment variable does not hold a non-negative integer
+    InvalidEnv { var: String, value: String },
+    /// The derived datacenter id does not fit in the datacenter bits
+    DatacenterIdOutOfRange { id: u64, max: u64 },
+    /// The derived machine id does not fit in the machine bits
+    MachineIdOutOfRange { id: u64, max: u64 },
+    /// The pod name does not end in `-<ordinal>`
+    NoOrdinal(String),
+    /// The hostname or local address could not be determined
+    Unavailable(&'static str),
+}
+
+impl fmt::Display for NodeIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeIdError::MissingEnv(var) => write!(f, "environment variable {} is not set", var),
+            NodeIdError::InvalidEnv { var, value } => {
+                write!(
+                    f,
+                    "environment variable {}={:?} is not a valid id",
+                    var, value
+                )
+            }
+            NodeIdError::DatacenterIdOutOfRange { id, max } => {
+                write!(f, "datacenter_id {} out of range (max {})", id, max)
+            }
+            NodeIdError::MachineIdOutOfRange { id, max } => {
+                write!(f, "machine_id {} out of range (max {})", id, max)
+            }
+            NodeIdError::NoOrdinal(name) => {
+                write!(f, "pod name {:?} does not end in an ordinal", name)
+            }
+            NodeIdError::Unavailable(what) => write!(f, "cannot determine the {}", what),
+        }
+    }
+}
+
+impl Error for NodeIdError {}
+
+/// A `datacenter_id` and `machine_id` pair that fits a [`BitLayout`]
+///
+/// ```
+/// use id_gnrt_rust_impl::{BitLayout, Snowflake};
+/// use id_gnrt_rust_impl::node::NodeId;
+///
+/// let layout = BitLayout::new(41, 0, 10, 12)?;
+/// let node = NodeId::from_pod_name(&layout, 0, "web-700")?;
+/// assert_eq!((node.datacenter_id(), node.machine_id()), (0, 700));
+/// let generator = Snowflake::builder().layout(layout).node_id(node).build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    datacenter_id: u64,
+    machine_id: u64,
+}
+
+impl NodeId {
+    /// Check `datacenter_id` and `machine_id` against `layout`
+    pub fn new(
+        layout: &BitLayout,
+        datacenter_id: u64,
+        machine_id: u64,
+    ) -> Result<Self, NodeIdError> {
+        if datacenter_id > layout.max_datacenter() {
+            return Err(NodeIdError::DatacenterIdOutOfRange {
+                id: datacenter_id,
+                max: layout.max_datacenter(),
+            });
+        }
+        if machine_id > layout.max_machine() {
+            return Err(NodeIdError::MachineIdOutOfRange {
+                id: machine_id,
+                max: layout.max_machine(),
+            });
+        }
+        Ok(NodeId {
+            datacenter_id,
+            machine_id,
+        })
+    }
+
+    /// The datacenter id
+    pub fn datacenter_id(&self) -> u64 {
+        self.datacenter_id
+    }
+
+    /// The machine id
+    pub fn machine_id(&self) -> u64 {
+        self.machine_id
+    }
+
+    /// Read [`DATACENTER_ENV`] and [`MACHINE_ENV`]
+    ///
+    /// The machine id is required; the datacenter id defaults to 0.
+    pub fn from_env(layout: &BitLayout) -> Result<Self, NodeIdError> {
+        Self::from_env_vars(layout, DATACENTER_ENV, MACHINE_ENV)
+    }
+
+    /// Read the ids from the named environment variables
+    pub fn from_env_vars(
+        layout: &BitLayout,
+        datacenter_var: &str,
+        machine_var: &str,
+    ) -> Result<Self, NodeIdError> {
+        from_lookup(layout, datacenter_var, machine_var, |var| {
+            env::var(var).ok()
+        })
+    }
+
+    /// Use the ordinal of a StatefulSet pod such as `web-7` as the machine id
+    pub fn from_pod_name(
+        layout: &BitLayout,
+        datacenter_id: u64,
+        pod_name: &str,
+    ) -> Result<Self, NodeIdError> {
+        let ordinal = pod_name
+            .rsplit_once('-')
+            .and_then(|(_, ordinal)| ordinal.parse().ok())
+            .ok_or_else(|| NodeIdError::NoOrdinal(pod_name.to_string()))?;
+        Self::new(layout, datacenter_id, ordinal)
+    }
+
+    /// Use the ordinal in this pod's hostname, which Kubernetes sets to the
+    /// pod name for StatefulSet members
+    pub fn from_statefulset(layout: &BitLayout, datacenter_id: u64) -> Result<Self, NodeIdError> {
+        Self::from_pod_name(layout, datacenter_id, &hostname()?)
+    }
+
+    /// Split the low bits of `addr` into a datacenter and machine id
+    ///
+    /// Takes as many bits as `layout` has datacenter and machine bits, 10 for
+    /// the default layout, and silently drops the rest. Unique as long as the
+    /// hosts share a subnet no larger than that, a /22 for the default.
+    pub fn from_ipv4(layout: &BitLayout, addr: Ipv4Addr) -> Self {
+        Self::truncate(layout, u64::from(u32::from(addr)))
+    }
+
+    /// Use the low bits of the IPv4 address this host routes outbound
+    /// traffic from
+    ///
+    /// No packets are sent: connecting a UDP socket only picks the route.
+    pub fn from_local_ipv4(layout: &BitLayout) -> Result<Self, NodeIdError> {
+        let unavailable = |_| NodeIdError::Unavailable("local IPv4 address");
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(unavailable)?;
+        socket
+            .connect((Ipv4Addr::new(192, 0, 2, 1), 9))
+            .map_err(unavailable)?;
+        match socket.local_addr().map_err(unavailable)?.ip() {
+            IpAddr::V4(addr) if !addr.is_unspecified() => Ok(Self::from_ipv4(layout, addr)),
+            _ => Err(NodeIdError::Unavailable("local IPv4 address")),
+        }
+    }
+
+    /// Derive both ids from a stable hash of `hostname`
+    ///
+    /// The hash is FNV-1a, so the result does not change between builds or
+    /// platforms. It is truncated to the datacenter and machine bits of
+    /// `layout`, so distinct hosts can still collide; prefer an ordinal or a
+    /// registry when the fleet is large.
+    pub fn from_hostname_hash(layout: &BitLayout, hostname: &str) -> Self {
+        let hash = hostname
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        Self::truncate(layout, hash ^ (hash >> 32))
+    }
+
+    /// Derive both ids from a stable hash of this host's name
+    pub fn from_hostname(layout: &BitLayout) -> Result<Self, NodeIdError> {
+        Ok(Self::from_hostname_hash(layout, &hostname()?))
+    }
+
+    /// Split the low bits of `bits` into the datacenter and machine ids of
+    /// `layout`, dropping the rest
+    fn truncate(layout: &BitLayout, bits: u64) -> Self {
+        NodeId {
+            datacenter_id: bits.checked_shr(layout.machine_bits() as u32).unwrap_or(0)
+                & layout.max_datacenter(),
+            machine_id: bits & layout.max_machine(),
+        }
+    }
+}
+
+fn from_lookup(
+    layout: &BitLayout,
+    datacenter_var: &str,
+    machine_var: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<NodeId, NodeIdError> {
+    let parse = |var: &str, value: String| {
+        value.trim().parse().map_err(|_| NodeIdError::InvalidEnv {
+            var: var.to_string(),
+            value,
+        })
+    };
+    let datacenter_id = match lookup(datacenter_var) {
+        Some(value) => parse(datacenter_var, value)?,
+        None => 0,
+    };
+    let machine_id = match lookup(machine_var) {
+        Some(value) => parse(machine_var, value)?,
+        None => return Err(NodeIdError::MissingEnv(machine_var.to_string())),
+    };
+    NodeId::new(layout, datacenter_id, machine_id)
+}
+
+/// This host's name, from `HOSTNAME` or the kernel
+fn hostname() -> Result<String, NodeIdError> {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or(NodeIdError::Unavailable("hostname"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT: BitLayout = BitLayout::DEFAULT;
+
+    fn lookup<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_from_env_vars() {
+        let node =
+            from_lookup(&DEFAULT, "DC", "M", lookup(&[("DC", "3"), ("M", " 17\n")])).unwrap();
+        assert_eq!(node, NodeId::new(&DEFAULT, 3, 17).unwrap());
+        let node = from_lookup(&DEFAULT, "DC", "M", lookup(&[("M", "4")])).unwrap();
+        assert_eq!(node, NodeId::new(&DEFAULT, 0, 4).unwrap());
+
+        assert_eq!(
+            from_lookup(&DEFAULT, "DC", "M", lookup(&[("DC", "1")])),
+            Err(NodeIdError::MissingEnv("M".to_string()))
+        );
+        assert_eq!(
+            from_lookup(&DEFAULT, "DC", "M", lookup(&[("M", "-1")])),
+            Err(NodeIdError::InvalidEnv {
+                var: "M".to_string(),
+                value: "-1".to_string()
+            })
+        );
+        assert_eq!(
+            from_lookup(&DEFAULT, "DC", "M", lookup(&[("DC", "32"), ("M", "1")])),
+            Err(NodeIdError::DatacenterIdOutOfRange { id: 32, max: 31 })
+        );
+    }
+
+    #[test]
+    fn test_from_pod_name() {
+        assert_eq!(
+            NodeId::from_pod_name(&DEFAULT, 1, "kafka-broker-31"),
+            NodeId::new(&DEFAULT, 1, 31)
+        );
+        assert_eq!(
+            NodeId::from_pod_name(&DEFAULT, 0, "web-32"),
+            Err(NodeIdError::MachineIdOutOfRange { id: 32, max: 31 })
+        );
+        // Machine ids follow the layout rather than the default
+        let wide = BitLayout::new(41, 0, 10, 12).unwrap();
+        assert_eq!(
+            NodeId::from_pod_name(&wide, 0, "web-100"),
+            NodeId::new(&wide, 0, 100)
+        );
+        assert_eq!(
+            NodeId::from_pod_name(&wide, 1, "web-100"),
+            Err(NodeIdError::DatacenterIdOutOfRange { id: 1, max: 0 })
+        );
+        for name in ["web", "web-", "web-x", "web-7a"] {
+            assert_eq!(
+                NodeId::from_pod_name(&DEFAULT, 0, name),
+                Err(NodeIdError::NoOrdinal(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_ipv4_uses_low_bits() {
+        // 10.0.3.45: low 10 bits are 0b11_0010_1101
+        let node = NodeId::from_ipv4(&DEFAULT, Ipv4Addr::new(10, 0, 3, 45));
+        assert_eq!((node.datacenter_id(), node.machine_id()), (25, 13));
+
+        let wide = BitLayout::new(41, 0, 10, 12).unwrap();
+        let node = NodeId::from_ipv4(&wide, Ipv4Addr::new(10, 0, 3, 45));
+        assert_eq!((node.datacenter_id(), node.machine_id()), (0, 813));
+        let narrow = BitLayout::new(41, 2, 3, 17).unwrap();
+        let node = NodeId::from_ipv4(&narrow, Ipv4Addr::new(10, 0, 3, 45));
+        assert_eq!((node.datacenter_id(), node.machine_id()), (1, 5));
+    }
+
+    #[test]
+    fn test_hostname_hash_is_stable() {
+        let node = NodeId::from_hostname_hash(&DEFAULT, "api-eu-west-1a.example.com");
+        assert_eq!(
+            node,
+            NodeId::from_hostname_hash(&DEFAULT, "api-eu-west-1a.example.com")
+        );
+        assert!(
+            node.datacenter_id() <= DEFAULT.max_datacenter()
+                && node.machine_id() <= DEFAULT.max_machine()
+        );
+        let distinct: std::collections::HashSet<NodeId> = (0..100)
+            .map(|i| NodeId::from_hostname_hash(&DEFAULT, &format!("host-{}", i)))
+            .collect();
+        assert!(distinct.len() > 90);
+    }
+}
@@ -0,0 +1,239 @@
+//! A persisted high-water timestamp that survives restarts.
+//!
+//! Without it, a generator restarted after the clock moved backwards starts
+//! from scratch and can reissue IDs from before the restart. With a
+//! [`StateFile`], the generator records a timestamp that no issued ID has
+//! reached, and on startup refuses to issue IDs below it.
+//!
+//! The mark is written ahead of time by a reserve window: one fsync covers
+//! every ID issued in the next `window`, rather than each ID costing one. A
+//! restart therefore begins up to `window` past the last issued ID, which the
+//! generator treats like a clock rollback of that size under its
+//! [`RollbackPolicy`](crate::RollbackPolicy).
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::error::SnowflakeError;
+
+/// How far ahead of the issued IDs the mark is written by default
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Where and how often a generator persists its high-water timestamp
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use id_gnrt_rust_impl::Snowflake;
+/// use id_gnrt_rust_impl::persist::StateFile;
+///
+/// let path = std::env::temp_dir().join(format!("snowflake-doc-{}.state", std::process::id()));
+/// let generator = Snowflake::builder()
+///     .state_file(StateFile::new(&path).window(Duration::from_millis(200)))
+///     .build()?;
+/// generator.next_id()?;
+/// # drop(generator);
+/// # std::fs::remove_file(&path).ok();
+/// # Ok::<(), id_gnrt_rust_impl::SnowflakeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateFile {
+    path: PathBuf,
+    window: Duration,
+}
+
+impl StateFile {
+    /// Persist to `path` with the [`DEFAULT_WINDOW`]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        StateFile {
+            path: path.into(),
+            window: DEFAULT_WINDOW,
+        }
+    }
+
+    /// Write the mark `window` ahead of the issued IDs
+    ///
+    /// Longer windows mean fewer fsyncs but a longer wait after a restart.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// The file holding the mark
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the persisted mark in Unix milliseconds; `None` if there is none
+    pub fn load(&self) -> Result<Option<u64>, SnowflakeError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(self.error(err)),
+        };
+        contents.trim().parse().map(Some).map_err(|_| {
+            SnowflakeError::StateFile(format!(
+                "{}: not a timestamp: {:?}",
+                self.path.display(),
+                contents.trim()
+            ))
+        })
+    }
+
+    /// Durably replace the mark with `mark`, in Unix milliseconds
+    ///
+    /// The mark is written to a temporary file, synced and renamed over the
+    /// old one, so a crash leaves either the old or the new mark in place.
+    pub fn store(&self, mark: u64) -> Result<(), SnowflakeError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let write = || -> io::Result<()> {
+            let mut file = File::create(&tmp)?;
+            writeln!(file, "{}", mark)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)?;
+            #[cfg(unix)]
+            if let Some(dir) = self.path.parent() {
+                let dir = if dir.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    dir
+                };
+                File::open(dir)?.sync_all()?;
+            }
+            Ok(())
+        };
+        write().map_err(|err| self.error(err))
+    }
+
+    fn error(&self, err: io::Error) -> SnowflakeError {
+        SnowflakeError::StateFile(format!("{}: {}", self.path.display(), err))
+    }
+}
+
+/// A running generator's view of its persisted mark
+#[derive(Debug)]
+pub(crate) struct HighWaterMark {
+    file: StateFile,
+    /// Epoch-relative timestamp no issued ID may reach without a new store
+    until: AtomicU64,
+    /// Serializes stores so the mark only moves forward
+    store: Mutex<()>,
+}
+
+impl HighWaterMark {
+    pub(crate) fn new(file: StateFile, until: u64) -> Self {
+        HighWaterMark {
+            file,
+            until: AtomicU64::new(until),
+            store: Mutex::new(()),
+        }
+    }
+
+    /// Make sure the persisted mark lies past the epoch-relative `timestamp`
+    pub(crate) fn cover(&self, timestamp: u64, epoch: u64) -> Result<(), SnowflakeError> {
+        if timestamp < self.until.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let _guard = self.store.lock().unwrap_or_else(|err| err.into_inner());
+        if timestamp < self.until.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let window = u64::try_from(self.file.window.as_millis()).unwrap_or(u64::MAX / 2);
+        let until = timestamp.saturating_add(1).saturating_add(window);
+        self.file.store(until.saturating_add(epoch))?;
+        self.until.store(until, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::epoch::Epoch;
+    use crate::generator::Snowflake;
+    use crate::layout::BitLayout;
+    use crate::rollback::RollbackPolicy;
+    use crate::testing::temp_path;
+
+    fn generator<'a>(
+        path: &Path,
+        clock: &'a ManualClock,
+        policy: RollbackPolicy,
+    ) -> Result<Snowflake<&'a ManualClock>, SnowflakeError> {
+        Snowflake::builder()
+            .layout(BitLayout::new(61, 0, 0, 2).unwrap())
+            .epoch(Epoch::UNIX)
+            .clock(clock)
+            .rollback_policy(policy)
+            .state_file(StateFile::new(path).window(Duration::from_millis(100)))
+            .build()
+    }
+
+    fn mark(path: &Path) -> u64 {
+        fs::read_to_string(path).unwrap().trim().parse().unwrap()
+    }
+
+    #[test]
+    fn test_mark_is_stored_once_per_window() {
+        let path = temp_path("window.state");
+        let clock = ManualClock::new(10_000);
+        let generator = generator(&path, &clock, RollbackPolicy::Error).unwrap();
+        generator.next_id().unwrap();
+        assert_eq!(mark(&path), 10_101);
+
+        fs::write(&path, "untouched").unwrap();
+        clock.set(10_100);
+        generator.next_ids(3).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "untouched");
+
+        clock.set(10_101);
+        generator.next_id().unwrap();
+        assert_eq!(mark(&path), 10_202);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_restart_never_issues_ids_below_the_mark() {
+        let path = temp_path("restart.state");
+        let clock = ManualClock::new(10_000);
+        let before = generator(&path, &clock, RollbackPolicy::Error)
+            .unwrap()
+            .next_id()
+            .unwrap();
+
+        // Restarted after the clock jumped back a second
+        clock.set(9_000);
+        let strict = generator(&path, &clock, RollbackPolicy::Error).unwrap();
+        assert!(matches!(
+            strict.next_id(),
+            Err(SnowflakeError::ClockMovedBackwards {
+                last: 10_100,
+                now: 9_000
+            })
+        ));
+        let logical = generator(&path, &clock, RollbackPolicy::LogicalClock).unwrap();
+        let after = logical.next_id().unwrap();
+        assert!(after > before);
+        assert_eq!(after.as_u64() >> 2, 10_101);
+        assert_eq!(mark(&path), 10_202);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_unreadable_mark_fails_the_build() {
+        let path = temp_path("corrupt.state");
+        let clock = ManualClock::new(10_000);
+        fs::write(&path, "yesterday\n").unwrap();
+        assert!(matches!(
+            generator(&path, &clock, RollbackPolicy::Error),
+            Err(SnowflakeError::StateFile(_))
+        ));
+        let _ = fs::remove_file(&path);
+    }
+}
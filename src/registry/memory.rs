@@ -0,0 +1,183 @@
+//! An in-process [`WorkerIdRegistry`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::clock::{Clock, SystemClock};
+use crate::layout::BitLayout;
+use crate::registry::{
+    Lease, RegistryError, WorkerId, WorkerIdRegistry, first_free, issued_through, ttl_millis,
+};
+
+/// The registry's record of one worker id
+#[derive(Debug)]
+struct Slot {
+    owner: String,
+    token: u64,
+    expires_at: u64,
+    /// Last ID timestamp reported by a released holder
+    issued_through: u64,
+}
+
+/// Leases worker ids held in memory, for tests and single-process setups
+#[derive(Debug)]
+pub struct MemoryRegistry<C = SystemClock> {
+    layout: BitLayout,
+    clock: C,
+    slots: Mutex<HashMap<WorkerId, Slot>>,
+}
+
+impl MemoryRegistry {
+    /// Lease every worker id `layout` can hold, timed by the system clock
+    pub fn new(layout: BitLayout) -> Self {
+        MemoryRegistry {
+            layout,
+            clock: SystemClock,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<C: Clock> MemoryRegistry<C> {
+    /// Time leases with `clock` instead
+    pub fn clock<D: Clock>(self, clock: D) -> MemoryRegistry<D> {
+        MemoryRegistry {
+            layout: self.layout,
+            clock,
+            slots: self.slots,
+        }
+    }
+
+    /// The owner recorded for `worker`, if its lease is live
+    pub fn owner(&self, worker: WorkerId) -> Option<String> {
+        let now = self.clock.now_millis();
+        let slots = self.slots.lock().unwrap();
+        slots
+            .get(&worker)
+            .filter(|slot| slot.expires_at > now)
+            .map(|slot| slot.owner.clone())
+    }
+}
+
+impl<C: Clock> WorkerIdRegistry for MemoryRegistry<C> {
+    fn acquire(&self, owner: &str, ttl: Duration) -> Result<Lease, RegistryError> {
+        let now = self.clock.now_millis();
+        let mut slots = self.slots.lock().unwrap();
+        let worker = first_free(&self.layout, |worker| {
+            slots.get(&worker).is_some_and(|slot| slot.expires_at > now)
+        })
+        .ok_or(RegistryError::Exhausted)?;
+
+        let expires_at = now.saturating_add(ttl_millis(ttl));
+        let slot = slots.entry(worker).or_insert(Slot {
+            owner: String::new(),
+            token: 0,
+            expires_at: 0,
+            issued_through: 0,
+        });
+        slot.issued_through = issued_through(slot.issued_through, slot.expires_at);
+        slot.owner = owner.to_string();
+        slot.token += 1;
+        slot.expires_at = expires_at;
+        Ok(Lease {
+            worker,
+            token: slot.token,
+            expires_at,
+            ttl,
+            issued_through: slot.issued_through,
+        })
+    }
+
+    fn heartbeat(&self, lease: &Lease) -> Result<Lease, RegistryError> {
+        let now = self.clock.now_millis();
+        let mut slots = self.slots.lock().unwrap();
+        match slots.get_mut(&lease.worker) {
+            Some(slot) if slot.token == lease.token && slot.expires_at > now => {
+                slot.expires_at = now.saturating_add(ttl_millis(lease.ttl));
+                Ok(Lease {
+                    expires_at: slot.expires_at,
+                    ..lease.clone()
+                })
+            }
+            _ => Err(RegistryError::LeaseLost(lease.worker)),
+        }
+    }
+
+    fn release(&self, lease: &Lease, issued_through: u64) -> Result<(), RegistryError> {
+        let mut slots = self.slots.lock().unwrap();
+        if let Some(slot) = slots.get_mut(&lease.worker)
+            && slot.token == lease.token
+        {
+            slot.issued_through = slot.issued_through.max(issued_through);
+            slot.expires_at = 0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const TTL: Duration = Duration::from_millis(100);
+
+    fn worker(datacenter_id: u64, machine_id: u64) -> WorkerId {
+        WorkerId {
+            datacenter_id,
+            machine_id,
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let clock = ManualClock::new(1000);
+        let registry = MemoryRegistry::new(BitLayout::new(41, 1, 1, 20).unwrap()).clock(&clock);
+        let leases: Vec<Lease> = (0..4)
+            .map(|i| registry.acquire(&format!("w{}", i), TTL).unwrap())
+            .collect();
+        assert_eq!(
+            leases.iter().map(|lease| lease.worker).collect::<Vec<_>>(),
+            [worker(0, 0), worker(0, 1), worker(1, 0), worker(1, 1)]
+        );
+        assert!(matches!(
+            registry.acquire("w4", TTL),
+            Err(RegistryError::Exhausted)
+        ));
+        assert_eq!(registry.owner(worker(1, 0)).as_deref(), Some("w2"));
+
+        registry.release(&leases[1], 1005).unwrap();
+        assert_eq!(registry.owner(worker(0, 1)), None);
+        let again = registry.acquire("w5", TTL).unwrap();
+        assert_eq!(again.worker, worker(0, 1));
+        assert_ne!(again.token, leases[1].token);
+        assert_eq!(again.issued_through, 1005);
+        // The old holder can neither renew nor release the new lease
+        assert!(registry.heartbeat(&leases[1]).is_err());
+        registry.release(&leases[1], 2000).unwrap();
+        assert_eq!(registry.owner(worker(0, 1)).as_deref(), Some("w5"));
+    }
+
+    #[test]
+    fn test_heartbeat_extends_until_expiry() {
+        let clock = ManualClock::new(1000);
+        let registry = MemoryRegistry::new(BitLayout::DEFAULT).clock(&clock);
+        let lease = registry.acquire("w", TTL).unwrap();
+        assert_eq!(lease.expires_at, 1100);
+
+        clock.set(1099);
+        let lease = registry.heartbeat(&lease).unwrap();
+        assert_eq!(lease.expires_at, 1199);
+
+        clock.set(1199);
+        assert!(matches!(
+            registry.heartbeat(&lease),
+            Err(RegistryError::LeaseLost(_))
+        ));
+        // The expired holder may have issued IDs until its lease lapsed
+        let other = registry.acquire("other", TTL).unwrap();
+        assert_eq!(other.worker, worker(0, 0));
+        assert_eq!(other.issued_through, 1199);
+    }
+}
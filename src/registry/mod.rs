@@ -0,0 +1,512 @@
+//! Fleet-wide worker id allocation with expiring leases.
+//!
+//! A [`WorkerIdRegistry`] hands each process a `(datacenter_id, machine_id)`
+//! pair for a limited time. The holder keeps it by heartbeating before the
+//! lease expires and gives it back with `release`, reporting the timestamp of
+//! the last ID it issued. A [`RegisteredSnowflake`] stops issuing IDs a safety
+//! margin before its lease expires, and starts after every ID earlier holders
+//! of its worker id may have issued, so two processes never issue the same ID.
+//!
+//! [`MemoryRegistry`] serves tests and single-process setups; with the
+//! `sqlite` feature, `SqliteRegistry` coordinates every process that can
+//! open the same database file.
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryRegistry;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteRegistry;
+
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use crate::batch::IdBatch;
+use crate::builder::SnowflakeBuilder;
+use crate::clock::Clock;
+use crate::error::SnowflakeError;
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+use crate::layout::BitLayout;
+
+/// A `(datacenter_id, machine_id)` pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WorkerId {
+    pub datacenter_id: u64,
+    pub machine_id: u64,
+}
+
+/// A time-limited claim on a worker id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub worker: WorkerId,
+    /// Distinguishes this claim from earlier and later claims on the same
+    /// worker id, so a holder whose lease expired cannot renew a successor's
+    pub token: u64,
+    /// Unix time in milliseconds at which the lease lapses
+    pub expires_at: u64,
+    /// How long each heartbeat extends the lease
+    pub ttl: Duration,
+    /// Unix time in milliseconds up to which earlier holders of the worker
+    /// id may have issued IDs; the new holder must issue only after it
+    pub issued_through: u64,
+}
+
+/// Errors reported by a [`WorkerIdRegistry`]
+#[derive(Debug)]
+pub enum RegistryError {
+    /// Every worker id in the layout is leased
+    Exhausted,
+    /// The lease expired or another process now holds the worker id
+    LeaseLost(WorkerId),
+    /// The generator could not be built with the leased worker id
+    Build(SnowflakeError),
+    /// The backing store failed
+    Backend(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Exhausted => write!(f, "every worker id is leased"),
+            RegistryError::LeaseLost(worker) => write!(
+                f,
+                "lease on datacenter_id {} machine_id {} was lost",
+                worker.datacenter_id, worker.machine_id
+            ),
+            RegistryError::Build(err) => err.fmt(f),
+            RegistryError::Backend(err) => write!(f, "worker id registry failed: {}", err),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RegistryError::Exhausted | RegistryError::LeaseLost(_) => None,
+            RegistryError::Build(err) => Some(err),
+            RegistryError::Backend(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+/// Leases worker ids to processes across a fleet
+pub trait WorkerIdRegistry: Send + Sync {
+    /// Lease the lowest free worker id for `ttl`, recording `owner` for
+    /// whoever inspects the registry
+    fn acquire(&self, owner: &str, ttl: Duration) -> Result<Lease, RegistryError>;
+
+    /// Extend `lease` by its TTL from now
+    ///
+    /// Fails with [`RegistryError::LeaseLost`] once the lease has expired,
+    /// even if nobody has claimed the worker id since.
+    fn heartbeat(&self, lease: &Lease) -> Result<Lease, RegistryError>;
+
+    /// Give the worker id back; releasing a lost lease does nothing
+    ///
+    /// `issued_through` is the Unix timestamp in milliseconds of the last ID
+    /// issued under the lease, handed to the next holder as its
+    /// [`Lease::issued_through`].
+    fn release(&self, lease: &Lease, issued_through: u64) -> Result<(), RegistryError>;
+}
+
+impl<R: WorkerIdRegistry + ?Sized> WorkerIdRegistry for Arc<R> {
+    fn acquire(&self, owner: &str, ttl: Duration) -> Result<Lease, RegistryError> {
+        (**self).acquire(owner, ttl)
+    }
+
+    fn heartbeat(&self, lease: &Lease) -> Result<Lease, RegistryError> {
+        (**self).heartbeat(lease)
+    }
+
+    fn release(&self, lease: &Lease, issued_through: u64) -> Result<(), RegistryError> {
+        (**self).release(lease, issued_through)
+    }
+}
+
+/// The lowest worker id in `layout` for which `taken` is false
+pub(crate) fn first_free(layout: &BitLayout, taken: impl Fn(WorkerId) -> bool) -> Option<WorkerId> {
+    (0..=layout.max_datacenter())
+        .flat_map(|datacenter_id| {
+            (0..=layout.max_machine()).map(move |machine_id| WorkerId {
+                datacenter_id,
+                machine_id,
+            })
+        })
+        .find(|&worker| !taken(worker))
+}
+
+/// Milliseconds in `ttl`, saturating
+pub(crate) fn ttl_millis(ttl: Duration) -> u64 {
+    u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Unix time up to which earlier holders of a worker id may have issued IDs
+///
+/// A released lease reported its last ID; an unreleased one may have issued
+/// IDs until it expired.
+pub(crate) fn issued_through(reported: u64, expires_at: u64) -> u64 {
+    reported.max(expires_at)
+}
+
+/// A generator that issues IDs only while it holds a registry lease
+///
+/// Call [`heartbeat`](RegisteredSnowflake::heartbeat) well within the TTL, or
+/// let [`spawn_heartbeat`](RegisteredSnowflake::spawn_heartbeat) do it. Once
+/// a heartbeat is refused, or the clock comes within a tenth of the TTL of
+/// the lease's expiry, every ID method fails with
+/// [`SnowflakeError::LeaseLost`]. That margin absorbs clock skew between the
+/// generator and the registry. IDs are checked against the timestamp they
+/// were actually issued at, so one that waited for the clock past the margin
+/// is never handed out. The lease is released on drop.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use id_gnrt_rust_impl::Snowflake;
+/// use id_gnrt_rust_impl::layout::BitLayout;
+/// use id_gnrt_rust_impl::registry::{MemoryRegistry, RegisteredSnowflake};
+///
+/// let registry = Arc::new(MemoryRegistry::new(BitLayout::DEFAULT));
+/// let generator = RegisteredSnowflake::acquire(
+///     Arc::clone(&registry),
+///     "worker-a",
+///     Duration::from_secs(30),
+///     Snowflake::builder(),
+/// )?;
+/// let id = generator.next_id()?;
+/// assert_eq!(id.machine(), generator.worker().machine_id);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct RegisteredSnowflake<R: WorkerIdRegistry, C: Clock> {
+    generator: Snowflake<C>,
+    registry: R,
+    lease: Mutex<Lease>,
+    worker: WorkerId,
+    /// Generator clock reading from which no IDs may be issued under the
+    /// current lease, a safety margin before it expires
+    valid_until: AtomicU64,
+    lost: AtomicBool,
+}
+
+impl<R: WorkerIdRegistry, C: Clock> RegisteredSnowflake<R, C> {
+    /// Lease a worker id from `registry` and build a generator with it
+    ///
+    /// The builder's datacenter and machine ids are replaced by the leased
+    /// ones, and IDs start after the lease's
+    /// [`issued_through`](Lease::issued_through). The lease is released again
+    /// if the generator cannot be built.
+    pub fn acquire(
+        registry: R,
+        owner: &str,
+        ttl: Duration,
+        builder: SnowflakeBuilder<C>,
+    ) -> Result<Self, RegistryError> {
+        let lease = registry.acquire(owner, ttl)?;
+        let built = builder
+            .datacenter_id(lease.worker.datacenter_id)
+            .machine_id(lease.worker.machine_id)
+            .build()
+            .and_then(|generator| {
+                generator.advance_past(lease.issued_through)?;
+                Ok(generator)
+            });
+        let generator = match built {
+            Ok(generator) => generator,
+            Err(err) => {
+                let _ = registry.release(&lease, lease.issued_through);
+                return Err(RegistryError::Build(err));
+            }
+        };
+        Ok(RegisteredSnowflake {
+            generator,
+            registry,
+            worker: lease.worker,
+            valid_until: AtomicU64::new(valid_until(&lease)),
+            lease: Mutex::new(lease),
+            lost: AtomicBool::new(false),
+        })
+    }
+
+    /// The leased worker id
+    pub fn worker(&self) -> WorkerId {
+        self.worker
+    }
+
+    /// The current lease
+    pub fn lease(&self) -> Lease {
+        self.lease.lock().unwrap().clone()
+    }
+
+    /// Whether IDs can still be issued under the lease
+    pub fn is_valid(&self) -> bool {
+        !self.lost.load(Ordering::Acquire)
+            && self.generator.clock().now_millis() < self.valid_until.load(Ordering::Acquire)
+    }
+
+    /// Renew the lease for another TTL
+    ///
+    /// A refused heartbeat marks the lease lost for good.
+    pub fn heartbeat(&self) -> Result<(), RegistryError> {
+        let mut lease = self.lease.lock().unwrap();
+        if self.lost.load(Ordering::Acquire) {
+            return Err(RegistryError::LeaseLost(self.worker));
+        }
+        match self.registry.heartbeat(&lease) {
+            Ok(renewed) => {
+                self.valid_until
+                    .store(valid_until(&renewed), Ordering::Release);
+                *lease = renewed;
+                Ok(())
+            }
+            Err(err) => {
+                if let RegistryError::LeaseLost(_) = err {
+                    self.lost.store(true, Ordering::Release);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Generate the next ID, waiting for the clock if needed
+    pub fn next_id(&self) -> Result<SnowflakeId, SnowflakeError> {
+        self.issue(|generator| generator.next_id())
+    }
+
+    /// Generate the next ID without waiting for the clock
+    pub fn try_next_id(&self) -> Result<SnowflakeId, SnowflakeError> {
+        self.issue(|generator| generator.try_next_id())
+    }
+
+    /// Generate the next ID, waiting at most `timeout` for the clock
+    pub fn next_id_timeout(&self, timeout: Duration) -> Result<SnowflakeId, SnowflakeError> {
+        self.issue(|generator| generator.next_id_timeout(timeout))
+    }
+
+    /// Claim `n` consecutive IDs
+    pub fn reserve(&self, n: usize) -> Result<IdBatch, SnowflakeError> {
+        self.issue(|generator| generator.reserve(n))
+    }
+
+    /// Generate `n` IDs
+    pub fn next_ids(&self, n: usize) -> Result<Vec<SnowflakeId>, SnowflakeError> {
+        self.issue(|generator| generator.next_ids(n))
+    }
+
+    /// Run `claim` if the lease is valid, and hand out what it claimed only
+    /// if the lease was still valid at the timestamp it claimed
+    ///
+    /// The generator's last timestamp is at least that of every claimed ID,
+    /// so checking it after the claim covers IDs that waited for the clock.
+    fn issue<T>(
+        &self,
+        claim: impl FnOnce(&Snowflake<C>) -> Result<T, SnowflakeError>,
+    ) -> Result<T, SnowflakeError> {
+        let lost = SnowflakeError::LeaseLost {
+            datacenter_id: self.worker.datacenter_id,
+            machine_id: self.worker.machine_id,
+        };
+        if !self.is_valid() {
+            return Err(lost);
+        }
+        let claimed = claim(&self.generator)?;
+        if self.lost.load(Ordering::Acquire)
+            || self.generator.last_timestamp() >= self.valid_until.load(Ordering::Acquire)
+        {
+            return Err(lost);
+        }
+        Ok(claimed)
+    }
+}
+
+/// The clock reading from which a holder of `lease` stops issuing IDs
+fn valid_until(lease: &Lease) -> u64 {
+    let margin = ttl_millis(lease.ttl) / 10;
+    lease.expires_at.saturating_sub(margin)
+}
+
+impl<R: WorkerIdRegistry + 'static, C: Clock + 'static> RegisteredSnowflake<R, C> {
+    /// Heartbeat every `interval` on a background thread
+    ///
+    /// The thread exits once the lease is lost or the generator is dropped.
+    /// Failures other than a lost lease are retried on the next tick.
+    pub fn spawn_heartbeat(self: &Arc<Self>, interval: Duration) -> thread::JoinHandle<()> {
+        let generator: Weak<Self> = Arc::downgrade(self);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                let Some(generator) = generator.upgrade() else {
+                    return;
+                };
+                if let Err(RegistryError::LeaseLost(_)) = generator.heartbeat() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+impl<R: WorkerIdRegistry, C: Clock> Drop for RegisteredSnowflake<R, C> {
+    fn drop(&mut self) {
+        let lease = self.lease.get_mut().unwrap_or_else(|err| err.into_inner());
+        let _ = self
+            .registry
+            .release(lease, self.generator.last_timestamp());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const TTL: Duration = Duration::from_millis(1000);
+
+    fn registry(clock: &Arc<ManualClock>) -> Arc<MemoryRegistry<Arc<ManualClock>>> {
+        Arc::new(
+            MemoryRegistry::new(BitLayout::new(41, 1, 1, 20).unwrap()).clock(Arc::clone(clock)),
+        )
+    }
+
+    fn generator(
+        registry: &Arc<MemoryRegistry<Arc<ManualClock>>>,
+        clock: &Arc<ManualClock>,
+    ) -> RegisteredSnowflake<Arc<MemoryRegistry<Arc<ManualClock>>>, Arc<ManualClock>> {
+        let builder = Snowflake::builder()
+            .layout(BitLayout::new(41, 1, 1, 20).unwrap())
+            .epoch(crate::epoch::Epoch::UNIX)
+            .clock(Arc::clone(clock));
+        RegisteredSnowflake::acquire(Arc::clone(registry), "test", TTL, builder).unwrap()
+    }
+
+    #[test]
+    fn test_stops_issuing_ids_once_the_lease_expires() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        let registry = registry(&clock);
+        let generator = generator(&registry, &clock);
+        generator.next_id().unwrap();
+
+        clock.set(10_999);
+        generator.heartbeat().unwrap();
+        clock.set(11_500);
+        generator.next_id().unwrap();
+
+        clock.set(12_000);
+        let lost = SnowflakeError::LeaseLost {
+            datacenter_id: 0,
+            machine_id: 0,
+        };
+        assert_eq!(generator.next_id(), Err(lost.clone()));
+        assert!(matches!(
+            generator.heartbeat(),
+            Err(RegistryError::LeaseLost(_))
+        ));
+        assert!(!generator.is_valid());
+        assert_eq!(generator.reserve(3).err(), Some(lost));
+    }
+
+    #[test]
+    fn test_lost_lease_stays_lost_after_takeover() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        let registry = registry(&clock);
+        let first = generator(&registry, &clock);
+        clock.set(11_000);
+        let second = generator(&registry, &clock);
+        assert_eq!(second.worker(), first.worker());
+        // The expired holder may have issued IDs until 11_000
+        assert!(matches!(
+            second.try_next_id(),
+            Err(SnowflakeError::WouldBlock { .. })
+        ));
+        clock.set(11_001);
+        assert!(second.next_id().is_ok());
+        assert!(first.heartbeat().is_err());
+        assert!(first.next_id().is_err());
+    }
+
+    #[test]
+    fn test_released_worker_id_does_not_repeat_ids_in_the_same_millisecond() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        let registry = registry(&clock);
+        let first = generator(&registry, &clock);
+        let worker = first.worker();
+        let id = first.next_id().unwrap();
+        drop(first);
+
+        let second = generator(&registry, &clock);
+        assert_eq!(second.worker(), worker);
+        assert!(matches!(
+            second.try_next_id(),
+            Err(SnowflakeError::WouldBlock { .. })
+        ));
+        clock.set(10_001);
+        assert_ne!(second.next_id().unwrap(), id);
+    }
+
+    #[test]
+    fn test_checks_the_lease_at_the_claimed_timestamp() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        let registry = registry(&clock);
+        let generator = generator(&registry, &clock);
+        // A tenth of the TTL before the lease expires, the sequence runs out
+        clock.set(10_899);
+        generator.reserve(1 << 20).unwrap();
+        assert!(generator.is_valid());
+
+        // The next ID waits for 10_900, which is past the margin
+        let advancer = {
+            let clock = Arc::clone(&clock);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                clock.set(10_900);
+            })
+        };
+        assert!(matches!(
+            generator.next_id(),
+            Err(SnowflakeError::LeaseLost { .. })
+        ));
+        advancer.join().unwrap();
+        assert!(!generator.is_valid());
+    }
+
+    #[test]
+    fn test_releases_on_drop_and_on_failed_build() {
+        let clock = Arc::new(ManualClock::new(10_000));
+        let registry = registry(&clock);
+        let first = generator(&registry, &clock);
+        let second = generator(&registry, &clock);
+        assert_ne!(first.worker(), second.worker());
+
+        // The next free worker id has datacenter 1, which this layout lacks
+        let result = RegisteredSnowflake::acquire(
+            Arc::clone(&registry),
+            "test",
+            TTL,
+            Snowflake::builder().layout(BitLayout::new(61, 0, 0, 2).unwrap()),
+        );
+        assert!(matches!(result, Err(RegistryError::Build(_))));
+        let probe = registry.acquire("probe", TTL).unwrap();
+        assert_eq!(
+            probe.worker,
+            WorkerId {
+                datacenter_id: 1,
+                machine_id: 0
+            }
+        );
+
+        drop(first);
+        assert_eq!(
+            registry.acquire("probe", TTL).unwrap().worker,
+            WorkerId {
+                datacenter_id: 0,
+                machine_id: 0
+            }
+        );
+    }
+}
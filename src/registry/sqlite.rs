@@ -0,0 +1,255 @@
+//! A [`WorkerIdRegistry`] kept in a SQLite database file.
+//!
+//! Available with the `sqlite` feature. Every process that opens the same
+//! file shares one pool of worker ids; acquisitions run in `IMMEDIATE`
+//! transactions, so two processes never lease the same id.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+
+use crate::clock::{Clock, SystemClock};
+use crate::layout::BitLayout;
+use crate::registry::{
+    Lease, RegistryError, WorkerId, WorkerIdRegistry, first_free, issued_through, ttl_millis,
+};
+
+/// How long to wait for another process holding the database lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snowflake_leases (
+    datacenter_id INTEGER NOT NULL,
+    machine_id INTEGER NOT NULL,
+    owner TEXT NOT NULL,
+    token INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    issued_through INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (datacenter_id, machine_id)
+)";
+
+impl From<rusqlite::Error> for RegistryError {
+    fn from(err: rusqlite::Error) -> Self {
+        RegistryError::Backend(Box::new(err))
+    }
+}
+
+/// Leases worker ids recorded in a SQLite database
+#[derive(Debug)]
+pub struct SqliteRegistry<C = SystemClock> {
+    layout: BitLayout,
+    clock: C,
+    conn: Mutex<Connection>,
+}
+
+impl SqliteRegistry {
+    /// Open or create the registry at `path`, leasing every worker id
+    /// `layout` can hold
+    pub fn open(path: impl AsRef<Path>, layout: BitLayout) -> Result<Self, RegistryError> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteRegistry {
+            layout,
+            clock: SystemClock,
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl<C: Clock> SqliteRegistry<C> {
+    /// Time leases with `clock` instead
+    ///
+    /// Every process sharing the file must agree on the time.
+    pub fn clock<D: Clock>(self, clock: D) -> SqliteRegistry<D> {
+        SqliteRegistry {
+            layout: self.layout,
+            clock,
+            conn: self.conn,
+        }
+    }
+}
+
+/// SQLite integers are signed; worker ids and times stay far below `i64::MAX`
+fn sql(value: u64) -> i64 {
+    value.min(i64::MAX as u64) as i64
+}
+
+impl<C: Clock> WorkerIdRegistry for SqliteRegistry<C> {
+    fn acquire(&self, owner: &str, ttl: Duration) -> Result<Lease, RegistryError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let now = self.clock.now_millis();
+
+        let live: HashSet<WorkerId> = tx
+            .prepare(
+                "SELECT datacenter_id, machine_id FROM snowflake_leases WHERE expires_at > ?1",
+            )?
+            .query_map([sql(now)], |row| {
+                Ok(WorkerId {
+                    datacenter_id: row.get::<_, i64>(0)? as u64,
+                    machine_id: row.get::<_, i64>(1)? as u64,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        let worker = first_free(&self.layout, |worker| live.contains(&worker))
+            .ok_or(RegistryError::Exhausted)?;
+
+        let previous = tx
+            .query_row(
+                "SELECT issued_through, expires_at FROM snowflake_leases
+                 WHERE datacenter_id = ?1 AND machine_id = ?2",
+                params![sql(worker.datacenter_id), sql(worker.machine_id)],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+            .optional()?;
+        let issued_through = previous.map_or(0, |(reported, expires_at)| {
+            issued_through(reported, expires_at)
+        });
+
+        let expires_at = now.saturating_add(ttl_millis(ttl));
+        let token: i64 = tx.query_row(
+            "INSERT INTO snowflake_leases
+                 (datacenter_id, machine_id, owner, token, expires_at, issued_through)
+             VALUES (?1, ?2, ?3, 1, ?4, ?5)
+             ON CONFLICT (datacenter_id, machine_id) DO UPDATE
+             SET owner = excluded.owner, token = token + 1, expires_at = excluded.expires_at,
+                 issued_through = excluded.issued_through
+             RETURNING token",
+            params![
+                sql(worker.datacenter_id),
+                sql(worker.machine_id),
+                owner,
+                sql(expires_at),
+                sql(issued_through)
+            ],
+            |row| row.get(0),
+        )?;
+        tx.commit()?;
+        Ok(Lease {
+            worker,
+            token: token as u64,
+            expires_at,
+            ttl,
+            issued_through,
+        })
+    }
+
+    fn heartbeat(&self, lease: &Lease) -> Result<Lease, RegistryError> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now_millis();
+        let expires_at = now.saturating_add(ttl_millis(lease.ttl));
+        let renewed = conn
+            .query_row(
+                "UPDATE snowflake_leases SET expires_at = ?1
+                 WHERE datacenter_id = ?2 AND machine_id = ?3 AND token = ?4 AND expires_at > ?5
+                 RETURNING expires_at",
+                params![
+                    sql(expires_at),
+                    sql(lease.worker.datacenter_id),
+                    sql(lease.worker.machine_id),
+                    sql(lease.token),
+                    sql(now)
+                ],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        match renewed {
+            Some(_) => Ok(Lease {
+                expires_at,
+                ..lease.clone()
+            }),
+            None => Err(RegistryError::LeaseLost(lease.worker)),
+        }
+    }
+
+    fn release(&self, lease: &Lease, issued_through: u64) -> Result<(), RegistryError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE snowflake_leases SET issued_through = max(issued_through, ?4), expires_at = 0
+             WHERE datacenter_id = ?1 AND machine_id = ?2 AND token = ?3",
+            params![
+                sql(lease.worker.datacenter_id),
+                sql(lease.worker.machine_id),
+                sql(lease.token),
+                sql(issued_through)
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::testing::temp_path;
+    use std::sync::Arc;
+
+    const TTL: Duration = Duration::from_millis(100);
+
+    fn open(path: &Path, clock: &Arc<ManualClock>) -> SqliteRegistry<Arc<ManualClock>> {
+        SqliteRegistry::open(path, BitLayout::new(41, 1, 1, 20).unwrap())
+            .unwrap()
+            .clock(Arc::clone(clock))
+    }
+
+    #[test]
+    fn test_connections_share_one_pool() {
+        let path = temp_path("registry-shared.db");
+        let clock = Arc::new(ManualClock::new(1000));
+        let first = open(&path, &clock);
+        let second = open(&path, &clock);
+
+        let a = first.acquire("a", TTL).unwrap();
+        let b = second.acquire("b", TTL).unwrap();
+        let c = first.acquire("c", TTL).unwrap();
+        let d = second.acquire("d", TTL).unwrap();
+        let workers: HashSet<WorkerId> = [&a, &b, &c, &d].iter().map(|l| l.worker).collect();
+        assert_eq!(workers.len(), 4);
+        assert!(matches!(
+            second.acquire("e", TTL),
+            Err(RegistryError::Exhausted)
+        ));
+
+        second.release(&a, 1005).unwrap();
+        let again = second.acquire("e", TTL).unwrap();
+        assert_eq!(again.worker, a.worker);
+        assert_eq!(again.token, a.token + 1);
+        assert_eq!(again.issued_through, 1005);
+        assert!(matches!(
+            first.heartbeat(&a),
+            Err(RegistryError::LeaseLost(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_heartbeat_extends_until_expiry() {
+        let path = temp_path("registry-heartbeat.db");
+        let clock = Arc::new(ManualClock::new(1000));
+        let registry = open(&path, &clock);
+        let lease = registry.acquire("w", TTL).unwrap();
+
+        clock.set(1099);
+        let lease = registry.heartbeat(&lease).unwrap();
+        assert_eq!(lease.expires_at, 1199);
+        // A reopened registry sees the renewed lease
+        let reopened = open(&path, &clock);
+        assert_ne!(reopened.acquire("x", TTL).unwrap().worker, lease.worker);
+
+        clock.set(1199);
+        assert!(matches!(
+            registry.heartbeat(&lease),
+            Err(RegistryError::LeaseLost(_))
+        ));
+        // The expired holder may have issued IDs until its lease lapsed
+        let next = registry.acquire("y", TTL).unwrap();
+        assert_eq!(next.worker, lease.worker);
+        assert_eq!(next.issued_through, 1199);
+        let _ = std::fs::remove_file(&path);
+    }
+}
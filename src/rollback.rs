@@ -0,0 +1,79 @@
+//! Handling of clocks that move backwards.
+//!
+//! A generator notices a rollback when its clock reads earlier than the
+//! timestamp of the last issued ID, for example after an NTP step. What it
+//! does next is chosen with a [`RollbackPolicy`], and every rollback it sees
+//! is reported to an optional listener as a [`RollbackEvent`], once when the
+//! clock is first seen behind rather than for each ID issued until it catches
+//! up.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What the generator does when its clock reads earlier than the last ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackPolicy {
+    /// Spin until the clock catches up, failing at once if it is more than
+    /// `max` behind
+    Wait { max: Duration },
+    /// Fail at once with [`SnowflakeError::ClockMovedBackwards`]
+    ///
+    /// [`SnowflakeError::ClockMovedBackwards`]: crate::SnowflakeError::ClockMovedBackwards
+    Error,
+    /// Keep issuing IDs at the last timestamp until its sequence numbers run
+    /// out, then fail
+    ReuseLastTimestamp,
+    /// Treat the last timestamp as a logical clock that moves forward by one
+    /// millisecond whenever its sequence numbers run out
+    LogicalClock,
+}
+
+impl Default for RollbackPolicy {
+    /// Wait however long it takes, as the generator always has
+    fn default() -> Self {
+        RollbackPolicy::Wait { max: Duration::MAX }
+    }
+}
+
+/// A clock rollback seen by the generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollbackEvent {
+    /// Timestamp of the last issued ID, in milliseconds since the Unix epoch
+    pub last_timestamp: u64,
+    /// What the clock read instead, in milliseconds since the Unix epoch
+    pub observed_timestamp: u64,
+    /// The policy applied to this rollback
+    pub policy: RollbackPolicy,
+}
+
+impl RollbackEvent {
+    /// How far the clock moved backwards
+    pub fn magnitude(&self) -> Duration {
+        Duration::from_millis(self.last_timestamp - self.observed_timestamp)
+    }
+}
+
+type Listener = dyn Fn(&RollbackEvent) + Send + Sync;
+
+/// Optional callback receiving every [`RollbackEvent`]
+#[derive(Clone, Default)]
+pub(crate) struct RollbackListener(Option<Arc<Listener>>);
+
+impl RollbackListener {
+    pub(crate) fn new(listener: impl Fn(&RollbackEvent) + Send + Sync + 'static) -> Self {
+        RollbackListener(Some(Arc::new(listener)))
+    }
+
+    pub(crate) fn notify(&self, event: &RollbackEvent) {
+        if let Some(listener) = &self.0 {
+            listener(event);
+        }
+    }
+}
+
+impl fmt::Debug for RollbackListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() { "Some(..)" } else { "None" })
+    }
+}
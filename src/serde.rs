@@ -0,0 +1,205 @@
+//! serde support for [`SnowflakeId`], available with the `serde` feature.
+//!
+//! IDs serialize as plain numbers by default. JavaScript loses precision on
+//! integers above 2^53, so JSON APIs can pick a string form per field with
+//! `#[serde(with = "...")]` and one of the modules below. In self-describing
+//! formats such as JSON, every mode deserializes both numbers and strings in
+//! its own format; formats that are not human-readable, such as bincode or
+//! postcard, read back exactly what the mode wrote.
+//!
+//! ```
+//! # use serde_derive::{Deserialize, Serialize};
+//! use id_gnrt_rust_impl::SnowflakeId;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     #[serde(with = "id_gnrt_rust_impl::serde::string")]
+//!     id: SnowflakeId,
+//! }
+//!
+//! let user = User { id: SnowflakeId::from_u64(1_541_815_603_604_623_360) };
+//! let json = serde_json::to_string(&user)?;
+//! assert_eq!(json, r#"{"id":"1541815603604623360"}"#);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use std::fmt;
+
+use ::serde::de::{self, Deserializer, Visitor};
+use ::serde::{Deserialize, Serialize, Serializer};
+
+use crate::id::{ParseIdError, SnowflakeId};
+
+impl Serialize for SnowflakeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        number::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SnowflakeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        number::deserialize(deserializer)
+    }
+}
+
+/// Serialize as a number; deserialize a number or a decimal string
+pub mod number {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(id.as_u64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SnowflakeId, D::Error> {
+        let visitor = IdVisitor {
+            parse: str::parse,
+            expecting: "a snowflake id as a number or decimal string",
+        };
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(visitor)
+        } else {
+            deserializer.deserialize_u64(visitor)
+        }
+    }
+}
+
+/// Serialize as a decimal string; deserialize a number or a decimal string
+pub mod string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SnowflakeId, D::Error> {
+        let visitor = IdVisitor {
+            parse: str::parse,
+            expecting: "a snowflake id as a number or decimal string",
+        };
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(visitor)
+        } else {
+            deserializer.deserialize_str(visitor)
+        }
+    }
+}
+
+/// Serialize as a base62 string; deserialize a number or a base62 string
+pub mod base62 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&id.to_base62())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SnowflakeId, D::Error> {
+        let visitor = IdVisitor {
+            parse: SnowflakeId::from_base62,
+            expecting: "a snowflake id as a number or base62 string",
+        };
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(visitor)
+        } else {
+            deserializer.deserialize_str(visitor)
+        }
+    }
+}
+
+/// Accepts unsigned and non-negative signed integers, and strings read
+/// with `parse`
+struct IdVisitor {
+    parse: fn(&str) -> Result<SnowflakeId, ParseIdError>,
+    expecting: &'static str,
+}
+
+impl Visitor<'_> for IdVisitor {
+    type Value = SnowflakeId;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<SnowflakeId, E> {
+        Ok(SnowflakeId::from_u64(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<SnowflakeId, E> {
+        SnowflakeId::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<SnowflakeId, E> {
+        (self.parse)(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ids {
+        plain: SnowflakeId,
+        #[serde(with = "string")]
+        string: SnowflakeId,
+        #[serde(with = "base62")]
+        base62: SnowflakeId,
+    }
+
+    #[test]
+    fn test_serialize_modes() {
+        let id = SnowflakeId::from_u64(u64::MAX);
+        let ids = Ids {
+            plain: id,
+            string: id,
+            base62: id,
+        };
+        let json = serde_json::to_string(&ids).unwrap();
+        assert_eq!(
+            json,
+            r#"{"plain":18446744073709551615,"string":"18446744073709551615","base62":"LygHa16AHYF"}"#
+        );
+        assert_eq!(serde_json::from_str::<Ids>(&json).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_roundtrip_in_binary_format() {
+        let id = SnowflakeId::from_u64(1_541_815_603_604_623_360);
+        let ids = Ids {
+            plain: id,
+            string: id,
+            base62: id,
+        };
+        let bytes = postcard::to_allocvec(&ids).unwrap();
+        assert_eq!(postcard::from_bytes::<Ids>(&bytes).unwrap(), ids);
+
+        let bytes = postcard::to_allocvec(&id).unwrap();
+        assert_eq!(postcard::from_bytes::<SnowflakeId>(&bytes).unwrap(), id);
+    }
+
+    #[test]
+    fn test_deserialize_number_or_string() {
+        let ids: Ids = serde_json::from_str(r#"{"plain":"42","string":42,"base62":42}"#).unwrap();
+        assert_eq!(ids.plain, SnowflakeId::from_u64(42));
+        assert_eq!(ids.string, SnowflakeId::from_u64(42));
+        assert_eq!(ids.base62, SnowflakeId::from_u64(42));
+    }
+
+    #[test]
+    fn test_deserialize_rejects_invalid() {
+        assert!(serde_json::from_str::<SnowflakeId>("-1").is_err());
+        assert!(serde_json::from_str::<SnowflakeId>("1.5").is_err());
+        let err = serde_json::from_str::<SnowflakeId>(r#""12a""#).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("invalid character 'a' at position 2")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! A gRPC ID service and client.
+//!
+//! Available with the `grpc` feature. The service is defined in
+//! `proto/snowflake.proto`, which other languages can compile into their own
+//! clients; the generated Rust types live in [`proto`].
+//!
+//! | RPC       | Response                                                   |
+//! |-----------|------------------------------------------------------------|
+//! | `NextId`  | one ID                                                     |
+//! | `NextIds` | a stream of `count` IDs, at most [`MAX_BATCH`]             |
+//! | `Decode`  | the fields of `id`, decoded with the generator's layout and epoch |
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::codegen::StdError;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Response, Status};
+
+use crate::batch::IdBatch;
+use crate::clock::{Clock, SystemClock};
+use crate::decode::decode_with;
+use crate::epoch::format_rfc3339;
+use crate::error::SnowflakeError;
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+use crate::server::MAX_BATCH;
+
+use proto::snowflake_service_client::SnowflakeServiceClient;
+use proto::snowflake_service_server::{SnowflakeService, SnowflakeServiceServer};
+use proto::{DecodeRequest, DecodeResponse, NextIdRequest, NextIdResponse, NextIdsRequest};
+
+/// Types generated from `proto/snowflake.proto`
+pub mod proto {
+    tonic::include_proto!("snowflake.v1");
+}
+
+/// Serves IDs from a shared generator over gRPC
+pub struct GrpcServer<C = SystemClock> {
+    listener: TcpListener,
+    generator: Arc<Snowflake<C>>,
+}
+
+impl<C: Clock + 'static> GrpcServer<C> {
+    /// Bind to `addr`; port 0 picks a free port
+    pub async fn bind(addr: impl ToSocketAddrs, generator: Arc<Snowflake<C>>) -> io::Result<Self> {
+        Ok(GrpcServer {
+            listener: TcpListener::bind(addr).await?,
+            generator,
+        })
+    }
+
+    /// The address the server is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serve requests until the listener fails
+    pub async fn serve(self) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(SnowflakeServiceServer::new(GrpcService {
+                generator: self.generator,
+            }))
+            .serve_with_incoming(TcpListenerStream::new(self.listener))
+            .await
+    }
+}
+
+/// The `SnowflakeService` implementation behind [`GrpcServer`]
+struct GrpcService<C> {
+    generator: Arc<Snowflake<C>>,
+}
+
+/// The stream of IDs answering `NextIds`
+type IdStream =
+    tokio_stream::Iter<std::iter::Map<IdBatch, fn(SnowflakeId) -> Result<NextIdResponse, Status>>>;
+
+fn unavailable(err: SnowflakeError) -> Status {
+    Status::unavailable(err.to_string())
+}
+
+#[tonic::async_trait]
+impl<C: Clock + 'static> SnowflakeService for GrpcService<C> {
+    type NextIdsStream = IdStream;
+
+    async fn next_id(
+        &self,
+        _request: Request<NextIdRequest>,
+    ) -> Result<Response<NextIdResponse>, Status> {
+        let id = self.generator.next_id_async().await.map_err(unavailable)?;
+        Ok(Response::new(NextIdResponse { id: id.as_u64() }))
+    }
+
+    async fn next_ids(
+        &self,
+        request: Request<NextIdsRequest>,
+    ) -> Result<Response<Self::NextIdsStream>, Status> {
+        let count = request.into_inner().count as usize;
+        if count > MAX_BATCH {
+            return Err(Status::invalid_argument(format!(
+                "count must be a number from 0 to {}",
+                MAX_BATCH
+            )));
+        }
+        // A batch may span several milliseconds, so claim it off the runtime
+        let generator = Arc::clone(&self.generator);
+        let batch = tokio::task::spawn_blocking(move || generator.reserve(count))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(unavailable)?;
+        let to_response: fn(SnowflakeId) -> Result<NextIdResponse, Status> =
+            |id| Ok(NextIdResponse { id: id.as_u64() });
+        Ok(Response::new(tokio_stream::iter(batch.map(to_response))))
+    }
+
+    async fn decode(
+        &self,
+        request: Request<DecodeRequest>,
+    ) -> Result<Response<DecodeResponse>, Status> {
+        let id = SnowflakeId::from_u64(request.into_inner().id);
+        let decoded = decode_with(id, self.generator.layout(), self.generator.epoch());
+        Ok(Response::new(DecodeResponse {
+            id: id.as_u64(),
+            timestamp: decoded.timestamp,
+            time: format_rfc3339(decoded.timestamp),
+            datacenter: decoded.datacenter,
+            machine: decoded.machine,
+            sequence: decoded.sequence,
+        }))
+    }
+}
+
+/// A client for a [`GrpcServer`], or any other `SnowflakeService`
+#[derive(Debug, Clone)]
+pub struct GrpcClient {
+    inner: SnowflakeServiceClient<Channel>,
+}
+
+impl GrpcClient {
+    /// Connect to `dst`, e.g. `"http://127.0.0.1:50051"`
+    pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+    where
+        D: TryInto<Endpoint>,
+        D::Error: Into<StdError>,
+    {
+        Ok(GrpcClient {
+            inner: SnowflakeServiceClient::connect(dst).await?,
+        })
+    }
+
+    /// Fetch one ID
+    pub async fn next_id(&mut self) -> Result<SnowflakeId, Status> {
+        let response = self.inner.next_id(NextIdRequest {}).await?;
+        Ok(SnowflakeId::from_u64(response.into_inner().id))
+    }
+
+    /// Fetch `count` IDs, in increasing order
+    pub async fn next_ids(&mut self, count: u32) -> Result<Vec<SnowflakeId>, Status> {
+        let mut stream = self
+            .inner
+            .next_ids(NextIdsRequest { count })
+            .await?
+            .into_inner();
+        let mut ids = Vec::with_capacity((count as usize).min(MAX_BATCH));
+        while let Some(response) = stream.next().await {
+            ids.push(SnowflakeId::from_u64(response?.id));
+        }
+        Ok(ids)
+    }
+
+    /// Decode `id` with the server's layout and epoch
+    pub async fn decode(&mut self, id: SnowflakeId) -> Result<DecodeResponse, Status> {
+        let response = self.inner.decode(DecodeRequest { id: id.as_u64() }).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -0,0 +1,312 @@
+//! A minimal HTTP/1.1 JSON service.
+//!
+//! | Method and path        | Response                                      |
+//! |------------------------|-----------------------------------------------|
+//! | `GET /id`              | `{"id":"…"}`                                  |
+//! | `GET /ids?count=N`     | `{"ids":["…",…]}`, at most [`MAX_BATCH`] IDs  |
+//! | `GET /decode/{id}`     | the fields of `id`, decoded with the generator's layout and epoch |
+//! | `GET /health`          | `{"status":"ok"}`                             |
+//! | `GET /metrics`         | Prometheus text format, with the `metrics` feature |
+//!
+//! IDs are sent as decimal strings because JavaScript cannot represent
+//! integers above 2^53. Errors are returned as `{"error":"…"}` with a 4xx or
+//! 5xx status.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use crate::clock::{Clock, SystemClock};
+use crate::decode::decode_with;
+use crate::epoch::format_rfc3339;
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+use crate::server::{MAX_BATCH, Protocol, TcpServer};
+
+/// Upper bound on the size of a request head, to fend off runaway clients
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+
+/// Upper bound on the body of a request; every route ignores it
+const MAX_BODY_BYTES: u64 = 8 * 1024;
+
+/// The HTTP/1.1 JSON protocol described in the [module docs](self)
+pub enum Http {}
+
+impl Protocol for Http {
+    fn handle_connection<C: Clock>(stream: TcpStream, generator: &Snowflake<C>) -> io::Result<()> {
+        handle_connection(stream, generator)
+    }
+}
+
+/// Serves IDs from a shared generator over HTTP
+pub type HttpServer<C = SystemClock> = TcpServer<Http, C>;
+
+/// A parsed request line and the headers the server cares about
+struct Request {
+    method: String,
+    target: String,
+    close: bool,
+}
+
+/// A response ready to be written
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Response {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: format!("{{\"error\":{}}}", json_string(message)),
+        }
+    }
+}
+
+fn handle_connection<C: Clock>(stream: TcpStream, generator: &Snowflake<C>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                write_response(&mut writer, &Response::error(400, &err.to_string()), true)?;
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let response = route(&request, generator);
+        write_response(&mut writer, &response, request.close)?;
+        if request.close {
+            return Ok(());
+        }
+    }
+}
+
+/// Read one request head, skipping any body; `None` on a clean EOF
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut line = String::new();
+    let mut head_bytes = read_head_line(reader, &mut line, 0)?;
+    if head_bytes == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => (method, target, version),
+        _ => return Err(invalid("malformed request line")),
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        close: version == "HTTP/1.0",
+    };
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        head_bytes += read_head_line(reader, &mut line, head_bytes)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| invalid("malformed content-length"))?;
+        } else if name.eq_ignore_ascii_case("connection") {
+            request.close = value.eq_ignore_ascii_case("close");
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(invalid("request body too large"));
+    }
+    io::copy(&mut reader.take(content_length), &mut io::sink())?;
+    Ok(Some(request))
+}
+
+/// Read one line of a request head into `line`, of which `head_bytes` were
+/// already read, without buffering past [`MAX_HEAD_BYTES`]
+fn read_head_line(
+    reader: &mut impl BufRead,
+    line: &mut String,
+    head_bytes: usize,
+) -> io::Result<usize> {
+    let remaining = MAX_HEAD_BYTES.saturating_sub(head_bytes) as u64;
+    let read = reader.by_ref().take(remaining + 1).read_line(line)?;
+    if head_bytes + read > MAX_HEAD_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request head too large",
+        ));
+    }
+    Ok(read)
+}
+
+fn route<C: Clock>(request: &Request, generator: &Snowflake<C>) -> Response {
+    if request.method != "GET" {
+        return Response::error(405, "only GET is supported");
+    }
+    let (path, query) = request
+        .target
+        .split_once('?')
+        .unwrap_or((&request.target, ""));
+
+    match path {
+        "/health" => Response::ok("{\"status\":\"ok\"}".to_string()),
+        #[cfg(feature = "metrics")]
+        "/metrics" => Response {
+            status: 200,
+            content_type: crate::metrics::CONTENT_TYPE,
+            body: generator.metrics().render(),
+        },
+        "/id" => match generator.next_id() {
+            Ok(id) => Response::ok(format!("{{\"id\":\"{}\"}}", id)),
+            Err(err) => Response::error(503, &err.to_string()),
+        },
+        "/ids" => {
+            let count = match query_param(query, "count").map(str::parse::<usize>) {
+                None => 1,
+                Some(Ok(count)) if count <= MAX_BATCH => count,
+                Some(_) => {
+                    return Response::error(
+                        400,
+                        &format!("count must be a number from 0 to {}", MAX_BATCH),
+                    );
+                }
+            };
+            match generator.next_ids(count) {
+                Ok(ids) => {
+                    let ids: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
+                    Response::ok(format!("{{\"ids\":[{}]}}", ids.join(",")))
+                }
+                Err(err) => Response::error(503, &err.to_string()),
+            }
+        }
+        _ => match path.strip_prefix("/decode/") {
+            Some(raw) => match raw.parse::<SnowflakeId>() {
+                Ok(id) => Response::ok(decoded_json(id, generator)),
+                Err(err) => Response::error(400, &format!("invalid id: {}", err)),
+            },
+            None => Response::error(404, "not found"),
+        },
+    }
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn decoded_json<C: Clock>(id: SnowflakeId, generator: &Snowflake<C>) -> String {
+    let decoded = decode_with(id, generator.layout(), generator.epoch());
+    format!(
+        "{{\"id\":\"{}\",\"timestamp\":{},\"time\":\"{}\",\"datacenter\":{},\"machine\":{},\"sequence\":{}}}",
+        id,
+        decoded.timestamp,
+        format_rfc3339(decoded.timestamp),
+        decoded.datacenter,
+        decoded.machine,
+        decoded.sequence
+    )
+}
+
+fn write_response(writer: &mut impl Write, response: &Response, close: bool) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        if close { "Connection: close\r\n" } else { "" },
+        response.body
+    )?;
+    writer.flush()
+}
+
+/// Quote `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if ch.is_control() => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request_skips_body() {
+        let raw = b"POST /id HTTP/1.1\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhelloGET /health HTTP/1.1\r\n\r\n";
+        let mut reader = &raw[..];
+        let first = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            (first.method.as_str(), first.target.as_str()),
+            ("POST", "/id")
+        );
+        assert!(first.close);
+        let second = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(second.target, "/health");
+        assert!(!second.close);
+        assert!(read_request(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_request_bounds_unterminated_lines() {
+        let mut endless = BufReader::new(io::repeat(b'a'));
+        let err = read_request(&mut endless).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut endless = BufReader::new(b"GET /id HTTP/1.1\r\nX-Pad: ".chain(io::repeat(b'a')));
+        let err = read_request(&mut endless).err().unwrap();
+        assert_eq!(err.to_string(), "request head too large");
+
+        let mut huge = BufReader::new(
+            b"GET /id HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n".chain(io::repeat(b'a')),
+        );
+        let err = read_request(&mut huge).err().unwrap();
+        assert_eq!(err.to_string(), "request body too large");
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,330 @@
+//! Network services that hand out IDs from a shared [`Snowflake`](crate::Snowflake).
+//!
+//! Available with the `server` feature.
+//!
+//! Each server owns a listener. The HTTP, RESP and Unix socket servers serve
+//! every connection on its own thread, within the [`Limits`] on idle time and
+//! open connections; the gRPC server, behind the `grpc` feature, runs on
+//! Tokio. The HTTP and RESP servers are a [`TcpServer`] speaking their
+//! [`Protocol`]. [`ServerOptions`] holds the settings common to the server
+//! binaries, and [`run_binary`] their shared startup.
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
+pub mod resp;
+#[cfg(unix)]
+pub mod uds;
+
+use std::error::Error;
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::builder::SnowflakeBuilder;
+use crate::clock::{Clock, SystemClock};
+use crate::epoch::Epoch;
+use crate::generator::Snowflake;
+use crate::layout::BitLayout;
+
+/// Largest batch a single request may ask for
+pub const MAX_BATCH: usize = 10_000;
+
+/// Pause after a failed `accept`, so a persistent error such as running out
+/// of file descriptors does not spin the accept loop
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+
+/// How long a connection may wait on a read or write before it is closed
+pub const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most connections served at once
+pub const MAX_CONNECTIONS: usize = 1024;
+
+/// Bounds on the connections a server keeps open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// A read or write blocked for this long closes the connection
+    pub timeout: Duration,
+    /// Connections accepted beyond this many are closed straight away
+    pub max_connections: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            timeout: IO_TIMEOUT,
+            max_connections: MAX_CONNECTIONS,
+        }
+    }
+}
+
+/// A stream accepted by one of the servers
+pub(crate) trait Connection: Send + 'static {
+    /// Fail reads and writes that block for longer than `timeout`
+    fn set_timeout(&self, timeout: Duration) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+#[cfg(unix)]
+impl Connection for std::os::unix::net::UnixStream {
+    fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+/// A wire protocol spoken by a [`TcpServer`]
+pub trait Protocol {
+    /// Serve requests on one connection until the client hangs up
+    fn handle_connection<C: Clock>(stream: TcpStream, generator: &Snowflake<C>) -> io::Result<()>;
+}
+
+/// Serves IDs from a shared generator over TCP with the protocol `P`
+pub struct TcpServer<P, C = SystemClock> {
+    listener: TcpListener,
+    generator: Arc<Snowflake<C>>,
+    limits: Limits,
+    protocol: PhantomData<P>,
+}
+
+impl<P: Protocol, C: Clock + 'static> TcpServer<P, C> {
+    /// Bind to `addr`; port 0 picks a free port
+    pub fn bind(addr: impl ToSocketAddrs, generator: Arc<Snowflake<C>>) -> io::Result<Self> {
+        Ok(TcpServer {
+            listener: TcpListener::bind(addr)?,
+            generator,
+            limits: Limits::default(),
+            protocol: PhantomData,
+        })
+    }
+
+    /// Serve connections within `limits` instead of the defaults
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The address the server is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections, one thread each
+    pub fn serve(self) -> io::Result<()> {
+        serve_connections(
+            self.listener.incoming(),
+            &self.generator,
+            self.limits,
+            P::handle_connection,
+        )
+    }
+}
+
+/// Serve every connection from `incoming` with `handle` on its own thread
+///
+/// A failed `accept`, such as running out of file descriptors, is reported
+/// and the loop carries on rather than taking the server down. Connections
+/// beyond `limits.max_connections` are closed unanswered, and idle ones are
+/// closed after `limits.timeout`.
+pub(crate) fn serve_connections<S, C>(
+    incoming: impl Iterator<Item = io::Result<S>>,
+    generator: &Arc<Snowflake<C>>,
+    limits: Limits,
+    handle: fn(S, &Snowflake<C>) -> io::Result<()>,
+) -> io::Result<()>
+where
+    S: Connection,
+    C: Clock + 'static,
+{
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in incoming {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("accept failed: {}", err);
+                thread::sleep(ACCEPT_BACKOFF);
+                continue;
+            }
+        };
+        if open.fetch_add(1, Ordering::AcqRel) >= limits.max_connections {
+            open.fetch_sub(1, Ordering::AcqRel);
+            eprintln!(
+                "refusing connection: {} already open",
+                limits.max_connections
+            );
+            continue;
+        }
+        let slot = OpenConnection(Arc::clone(&open));
+        if stream.set_timeout(limits.timeout).is_err() {
+            continue;
+        }
+        let generator = Arc::clone(generator);
+        thread::spawn(move || {
+            let _slot = slot;
+            // A client hanging up or idling mid-request is not the server's
+            // problem
+            let _ = handle(stream, &generator);
+        });
+    }
+    Ok(())
+}
+
+/// Counts a connection as open until dropped
+struct OpenConnection(Arc<AtomicUsize>);
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// The `main` of a server binary
+///
+/// Prints `usage` for `-h` or `--help`, parses `args` with
+/// [`ServerOptions::parse`] on top of the defaults for `listen`, builds the
+/// generator and hands both to `serve`. Bad arguments exit with status 2 and
+/// any other failure with status 1.
+pub fn run_binary(
+    name: &str,
+    usage: &str,
+    args: Vec<String>,
+    listen: SocketAddr,
+    serve: impl FnOnce(&ServerOptions, Arc<Snowflake>) -> Result<(), Box<dyn Error>>,
+) -> ExitCode {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", usage);
+        return ExitCode::SUCCESS;
+    }
+    let options = match ServerOptions::new(listen).parse(args) {
+        Ok(options) => options,
+        Err(err) => return usage_error(name, &err),
+    };
+    let result = options
+        .builder()
+        .build()
+        .map_err(Box::from)
+        .and_then(|generator| serve(&options, Arc::new(generator)));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Report bad command-line arguments to a server binary's user
+pub fn usage_error(name: &str, err: &str) -> ExitCode {
+    eprintln!("error: {}\n\nRun `{} --help` for usage.", err, name);
+    ExitCode::from(2)
+}
+
+/// Settings shared by the server binaries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerOptions {
+    pub listen: SocketAddr,
+    pub datacenter_id: u64,
+    pub machine_id: u64,
+    pub epoch: Epoch,
+    pub layout: BitLayout,
+}
+
+impl ServerOptions {
+    /// Defaults for a server listening on `listen`
+    pub fn new(listen: SocketAddr) -> Self {
+        ServerOptions {
+            listen,
+            datacenter_id: 0,
+            machine_id: 0,
+            epoch: Epoch::TWITTER,
+            layout: BitLayout::DEFAULT,
+        }
+    }
+
+    /// Parse `--listen`, `--datacenter`, `--machine`, `--epoch` and
+    /// `--layout` flags, each given as `--flag value` or `--flag=value`
+    pub fn parse<I>(mut self, args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let value = value
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            let invalid = |err: &dyn std::fmt::Display| {
+                format!("invalid value {:?} for {}: {}", value, flag, err)
+            };
+            match flag.as_str() {
+                "--listen" => self.listen = value.parse().map_err(|err| invalid(&err))?,
+                "--datacenter" => {
+                    self.datacenter_id = value.parse().map_err(|err| invalid(&err))?
+                }
+                "--machine" => self.machine_id = value.parse().map_err(|err| invalid(&err))?,
+                "--epoch" => self.epoch = value.parse().map_err(|err| invalid(&err))?,
+                "--layout" => self.layout = value.parse().map_err(|err| invalid(&err))?,
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(self)
+    }
+
+    /// A generator builder configured from these options
+    pub fn builder(&self) -> SnowflakeBuilder {
+        SnowflakeBuilder::new()
+            .datacenter_id(self.datacenter_id)
+            .machine_id(self.machine_id)
+            .epoch(self.epoch)
+            .layout(self.layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = ServerOptions::new("127.0.0.1:1".parse().unwrap())
+            .parse(args(&[
+                "--listen=0.0.0.0:9000",
+                "--datacenter",
+                "3",
+                "--machine=4",
+                "--layout",
+                "41,0,10,12",
+            ]))
+            .unwrap();
+        assert_eq!(options.listen, "0.0.0.0:9000".parse().unwrap());
+        assert_eq!((options.datacenter_id, options.machine_id), (3, 4));
+        assert_eq!(options.epoch, Epoch::TWITTER);
+        assert!(options.builder().build().is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_flags() {
+        let options = ServerOptions::new("127.0.0.1:1".parse().unwrap());
+        assert!(options.clone().parse(args(&["--machine"])).is_err());
+        assert!(options.clone().parse(args(&["--machine", "x"])).is_err());
+        assert!(options.parse(args(&["--verbose", "1"])).is_err());
+    }
+}
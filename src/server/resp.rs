@@ -0,0 +1,279 @@
+//! A Redis-protocol (RESP2) ID service.
+//!
+//! Existing Redis clients can fetch IDs by sending custom commands:
+//!
+//! | Command          | Reply                                                  |
+//! |------------------|--------------------------------------------------------|
+//! | `NEXTID`         | integer                                                |
+//! | `NEXTIDS n`      | array of `n` integers, at most [`MAX_BATCH`]           |
+//! | `DECODE id`      | flat array of field names and values, like `HGETALL`   |
+//! | `PING [message]` | `PONG`, or the message                                 |
+//! | `METRICS`        | Prometheus text format, with the `metrics` feature     |
+//! | `QUIT`           | `OK`, then the connection is closed                    |
+//!
+//! Commands may be sent as RESP arrays of bulk strings, as client libraries
+//! do, or as inline space-separated lines, as typed into `telnet`.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use crate::clock::{Clock, SystemClock};
+use crate::decode::decode_with;
+use crate::epoch::format_rfc3339;
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+use crate::server::{MAX_BATCH, Protocol, TcpServer};
+
+/// Upper bound on the number of arguments in one command
+const MAX_ARGS: usize = 16;
+/// Upper bound on the length of one argument or inline command
+const MAX_ARG_BYTES: usize = 4096;
+
+/// The RESP2 command set described in the [module docs](self)
+pub enum Resp {}
+
+impl Protocol for Resp {
+    fn handle_connection<C: Clock>(stream: TcpStream, generator: &Snowflake<C>) -> io::Result<()> {
+        handle_connection(stream, generator)
+    }
+}
+
+/// Serves IDs from a shared generator over the Redis protocol
+pub type RespServer<C = SystemClock> = TcpServer<Resp, C>;
+
+/// A reply frame
+#[derive(Debug, PartialEq, Eq)]
+enum Reply {
+    Simple(&'static str),
+    Error(String),
+    Integer(u64),
+    Bulk(String),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            Reply::Simple(s) => write!(out, "+{}\r\n", s),
+            Reply::Error(s) => write!(out, "-{}\r\n", s),
+            Reply::Integer(n) => write!(out, ":{}\r\n", n),
+            Reply::Bulk(s) => write!(out, "${}\r\n{}\r\n", s.len(), s),
+            Reply::Array(items) => {
+                write!(out, "*{}\r\n", items.len())?;
+                items.iter().try_for_each(|item| item.write_to(out))
+            }
+        }
+    }
+}
+
+fn handle_connection<C: Clock>(stream: TcpStream, generator: &Snowflake<C>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = io::BufWriter::new(stream);
+    loop {
+        let args = match read_command(&mut reader) {
+            Ok(Some(args)) => args,
+            Ok(None) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                Reply::Error(format!("ERR Protocol error: {}", err)).write_to(&mut writer)?;
+                return writer.flush();
+            }
+            Err(err) => return Err(err),
+        };
+        if args.is_empty() {
+            continue;
+        }
+
+        let quit = args[0].eq_ignore_ascii_case("QUIT");
+        execute(&args, generator).write_to(&mut writer)?;
+        // Pipelined commands are answered together
+        if reader.buffer().is_empty() || quit {
+            writer.flush()?;
+        }
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Read one command as a list of arguments; `None` on a clean EOF
+fn read_command(reader: &mut impl BufRead) -> io::Result<Option<Vec<String>>> {
+    let Some(line) = read_line(reader)? else {
+        return Ok(None);
+    };
+    let Some(count) = line.strip_prefix('*') else {
+        // Inline command
+        return Ok(Some(line.split_whitespace().map(str::to_string).collect()));
+    };
+
+    let count = parse_length(count, MAX_ARGS)?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let header = read_line(reader)?.ok_or_else(unexpected_eof)?;
+        let len = header
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("expected '$'"))?;
+        let len = parse_length(len, MAX_ARG_BYTES)?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg)?;
+        if !arg.ends_with(b"\r\n") {
+            return Err(invalid("bulk string not terminated by CRLF"));
+        }
+        arg.truncate(len);
+        args.push(String::from_utf8(arg).map_err(|_| invalid("argument is not UTF-8"))?);
+    }
+    Ok(Some(args))
+}
+
+/// Read a CRLF-terminated line without its terminator
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = reader
+        .by_ref()
+        .take(MAX_ARG_BYTES as u64 + 2)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(if read > MAX_ARG_BYTES {
+            invalid("line too long")
+        } else {
+            unexpected_eof()
+        });
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("line is not UTF-8"))?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn parse_length(s: &str, max: usize) -> io::Result<usize> {
+    match s.parse() {
+        Ok(len) if len <= max => Ok(len),
+        _ => Err(invalid("invalid length")),
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::from(io::ErrorKind::UnexpectedEof)
+}
+
+fn execute<C: Clock>(args: &[String], generator: &Snowflake<C>) -> Reply {
+    let name = args[0].to_ascii_uppercase();
+    let wrong_arity = || {
+        Reply::Error(format!(
+            "ERR wrong number of arguments for '{}' command",
+            args[0].to_ascii_lowercase()
+        ))
+    };
+
+    match (name.as_str(), &args[1..]) {
+        ("NEXTID", []) => match generator.next_id() {
+            Ok(id) => Reply::Integer(id.as_u64()),
+            Err(err) => Reply::Error(format!("ERR {}", err)),
+        },
+        ("NEXTIDS", [count]) => match count.parse::<usize>() {
+            Ok(count) if count <= MAX_BATCH => match generator.next_ids(count) {
+                Ok(ids) => Reply::Array(
+                    ids.into_iter()
+                        .map(|id| Reply::Integer(id.as_u64()))
+                        .collect(),
+                ),
+                Err(err) => Reply::Error(format!("ERR {}", err)),
+            },
+            _ => Reply::Error(format!(
+                "ERR count must be an integer from 0 to {}",
+                MAX_BATCH
+            )),
+        },
+        ("DECODE", [id]) => match id.parse::<SnowflakeId>() {
+            Ok(id) => {
+                let decoded = decode_with(id, generator.layout(), generator.epoch());
+                let field = |name: &str| Reply::Bulk(name.to_string());
+                Reply::Array(vec![
+                    field("timestamp"),
+                    Reply::Integer(decoded.timestamp),
+                    field("time"),
+                    Reply::Bulk(format_rfc3339(decoded.timestamp)),
+                    field("datacenter"),
+                    Reply::Integer(decoded.datacenter),
+                    field("machine"),
+                    Reply::Integer(decoded.machine),
+                    field("sequence"),
+                    Reply::Integer(decoded.sequence),
+                ])
+            }
+            Err(err) => Reply::Error(format!("ERR invalid id: {}", err)),
+        },
+        #[cfg(feature = "metrics")]
+        ("METRICS", []) => Reply::Bulk(generator.metrics().render()),
+        ("PING", []) => Reply::Simple("PONG"),
+        ("PING", [message]) => Reply::Bulk(message.clone()),
+        ("QUIT", _) => Reply::Simple("OK"),
+        // Sent by redis-cli and some client libraries when connecting
+        ("COMMAND", _) => Reply::Array(Vec::new()),
+        ("CLIENT", _) => Reply::Simple("OK"),
+        ("NEXTID" | "NEXTIDS" | "DECODE" | "PING", _) => wrong_arity(),
+        #[cfg(feature = "metrics")]
+        ("METRICS", _) => wrong_arity(),
+        _ => Reply::Error(format!("ERR unknown command '{}'", args[0])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(reply: &Reply) -> String {
+        let mut out = Vec::new();
+        reply.write_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_read_array_and_inline_commands() {
+        let raw = b"*2\r\n$7\r\nNEXTIDS\r\n$1\r\n5\r\nping hello\r\n";
+        let mut reader = &raw[..];
+        assert_eq!(
+            read_command(&mut reader).unwrap(),
+            Some(vec!["NEXTIDS".to_string(), "5".to_string()])
+        );
+        assert_eq!(
+            read_command(&mut reader).unwrap(),
+            Some(vec!["ping".to_string(), "hello".to_string()])
+        );
+        assert_eq!(read_command(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_rejects_malformed_frames() {
+        for raw in [
+            &b"*1\r\n:5\r\n"[..],
+            b"*1\r\n$3\r\nabcd\r\n",
+            b"*x\r\n",
+            b"*100\r\n",
+            b"*1\r\n$99999\r\n",
+        ] {
+            let err = read_command(&mut &raw[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", raw);
+        }
+        let err = read_command(&mut &b"*1\r\n$3\r\nab"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_encode_replies() {
+        let reply = Reply::Array(vec![
+            Reply::Integer(42),
+            Reply::Bulk("time".to_string()),
+            Reply::Simple("OK"),
+            Reply::Error("ERR nope".to_string()),
+        ]);
+        assert_eq!(
+            encode(&reply),
+            "*4\r\n:42\r\n$4\r\ntime\r\n+OK\r\n-ERR nope\r\n"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! A Unix domain socket ID daemon with a compact binary protocol.
+//!
+//! Meant for sidecars, where an HTTP stack is too heavy for the hot path.
+//! All integers are big-endian:
+//!
+//! | Frame    | Layout                                                        |
+//! |----------|---------------------------------------------------------------|
+//! | request  | `u32` count, at most [`MAX_BATCH`]                            |
+//! | response | `u32` count, then that many `u64` IDs in increasing order     |
+//! | error    | `u32` [`ERROR_MARKER`], `u32` length, then a UTF-8 message    |
+//!
+//! A connection carries any number of requests, each answered in order.
+
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::clock::{Clock, SystemClock};
+use crate::generator::Snowflake;
+use crate::id::SnowflakeId;
+use crate::server::{Limits, MAX_BATCH, serve_connections};
+
+/// Count sent in place of a response count to mark an error frame
+pub const ERROR_MARKER: u32 = u32::MAX;
+
+/// Serves IDs from a shared generator over a Unix domain socket
+pub struct UdsServer<C = SystemClock> {
+    listener: UnixListener,
+    path: PathBuf,
+    generator: Arc<Snowflake<C>>,
+    limits: Limits,
+}
+
+impl<C: Clock + 'static> UdsServer<C> {
+    /// Bind to the socket at `path`
+    ///
+    /// A socket file left behind by a server that is no longer running is
+    /// replaced; a live one makes this fail with `AddrInUse`.
+    pub fn bind(path: impl AsRef<Path>, generator: Arc<Snowflake<C>>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let listener = match UnixListener::bind(path) {
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse && is_stale(path) => {
+                std::fs::remove_file(path)?;
+                UnixListener::bind(path)?
+            }
+            result => result?,
+        };
+        Ok(UdsServer {
+            listener,
+            path: path.to_path_buf(),
+            generator,
+            limits: Limits::default(),
+        })
+    }
+
+    /// Serve connections within `limits` instead of the defaults
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The path the server is listening on
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Accept connections, one thread each
+    pub fn serve(self) -> io::Result<()> {
+        serve_connections(
+            self.listener.incoming(),
+            &self.generator,
+            self.limits,
+            handle_connection,
+        )
+    }
+}
+
+/// Whether `path` is a socket nobody is accepting on
+fn is_stale(path: &Path) -> bool {
+    matches!(
+        UnixStream::connect(path),
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused
+    )
+}
+
+fn handle_connection<C: Clock>(mut stream: UnixStream, generator: &Snowflake<C>) -> io::Result<()> {
+    let mut header = [0; 4];
+    let mut response = Vec::new();
+    loop {
+        match stream.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        }
+        let count = u32::from_be_bytes(header) as usize;
+
+        response.clear();
+        if count > MAX_BATCH {
+            write_error(
+                &mut response,
+                &format!("count must be a number from 0 to {}", MAX_BATCH),
+            );
+        } else {
+            match generator.reserve(count) {
+                Ok(batch) => {
+                    response.extend_from_slice(&(count as u32).to_be_bytes());
+                    for id in batch {
+                        response.extend_from_slice(&id.as_u64().to_be_bytes());
+                    }
+                }
+                Err(err) => write_error(&mut response, &err.to_string()),
+            }
+        }
+        stream.write_all(&response)?;
+    }
+}
+
+fn write_error(out: &mut Vec<u8>, message: &str) {
+    out.extend_from_slice(&ERROR_MARKER.to_be_bytes());
+    out.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    out.extend_from_slice(message.as_bytes());
+}
+
+/// A client for a [`UdsServer`]
+///
+/// Errors reported by the server surface as [`io::ErrorKind::Other`] with the
+/// server's message.
+#[derive(Debug)]
+pub struct UdsClient {
+    stream: UnixStream,
+    buf: Vec<u8>,
+}
+
+impl UdsClient {
+    /// Connect to the socket at `path`
+    pub fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(UdsClient {
+            stream: UnixStream::connect(path)?,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Fetch one ID
+    pub fn next_id(&mut self) -> io::Result<SnowflakeId> {
+        self.request(1)?;
+        Ok(self.id_at(0))
+    }
+
+    /// Fetch `count` IDs, in increasing order
+    pub fn next_ids(&mut self, count: u32) -> io::Result<Vec<SnowflakeId>> {
+        self.request(count)?;
+        Ok((0..count as usize).map(|i| self.id_at(i)).collect())
+    }
+
+    /// Send one request and read the IDs of its response into `buf`
+    fn request(&mut self, count: u32) -> io::Result<()> {
+        self.stream.write_all(&count.to_be_bytes())?;
+        let mut header = [0; 4];
+        self.stream.read_exact(&mut header)?;
+        match u32::from_be_bytes(header) {
+            ERROR_MARKER => {
+                self.stream.read_exact(&mut header)?;
+                let mut message = vec![0; u32::from_be_bytes(header) as usize];
+                self.stream.read_exact(&mut message)?;
+                Err(io::Error::other(String::from_utf8_lossy(&message)))
+            }
+            n if n == count => {
+                self.buf.resize(count as usize * 8, 0);
+                self.stream.read_exact(&mut self.buf)
+            }
+            n => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("asked for {} ids, server sent {}", count, n),
+            )),
+        }
+    }
+
+    fn id_at(&self, index: usize) -> SnowflakeId {
+        let bytes = self.buf[index * 8..][..8].try_into().unwrap();
+        SnowflakeId::from_u64(u64::from_be_bytes(bytes))
+    }
+}
@@ -0,0 +1,13 @@
+//! Helpers shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A path in the temp directory unique to this test process and `name`, with
+/// anything an earlier run left there removed
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("snowflake-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
@@ -0,0 +1,282 @@
+//! Machine id leasing for several processes on one host.
+//!
+//! [`WorkerIdProvider`] claims the first free machine id by taking an
+//! exclusive lock on a per-id file in a shared directory. The operating
+//! system drops the lock when the process exits, so a crashed worker never
+//! strands its id.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use crate::builder::SnowflakeBuilder;
+use crate::clock::Clock;
+use crate::error::SnowflakeError;
+use crate::generator::Snowflake;
+use crate::layout::MAX_MACHINE;
+
+/// Errors reported while leasing a machine id
+#[derive(Debug)]
+pub enum LeaseError {
+    /// Every machine id up to `max` is already leased
+    NoFreeId { max: u64 },
+    /// The lease directory or a lock file could not be used
+    Io(io::Error),
+    /// The generator could not be built with the leased id
+    Build(SnowflakeError),
+}
+
+impl fmt::Display for LeaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaseError::NoFreeId { max } => {
+                write!(f, "every machine_id from 0 to {} is leased", max)
+            }
+            LeaseError::Io(err) => write!(f, "cannot lease a machine_id: {}", err),
+            LeaseError::Build(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for LeaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LeaseError::NoFreeId { .. } => None,
+            LeaseError::Io(err) => Some(err),
+            LeaseError::Build(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for LeaseError {
+    fn from(err: io::Error) -> Self {
+        LeaseError::Io(err)
+    }
+}
+
+impl From<SnowflakeError> for LeaseError {
+    fn from(err: SnowflakeError) -> Self {
+        LeaseError::Build(err)
+    }
+}
+
+/// Hands out machine ids by locking `machine-{id}.lock` files in a directory
+///
+/// ```
+/// use id_gnrt_rust_impl::worker::WorkerIdProvider;
+/// use id_gnrt_rust_impl::Snowflake;
+///
+/// let dir = std::env::temp_dir().join(format!("snowflake-doc-leases-{}", std::process::id()));
+/// let provider = WorkerIdProvider::new(&dir);
+/// let generator = provider.build(Snowflake::builder().datacenter_id(1))?;
+/// let id = generator.next_id()?;
+/// assert_eq!(id.machine(), generator.lease().machine_id());
+/// # drop(generator);
+/// # std::fs::remove_dir_all(&dir).ok();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkerIdProvider {
+    dir: PathBuf,
+    max_machine_id: u64,
+}
+
+impl WorkerIdProvider {
+    /// Lease ids from `0..=MAX_MACHINE` using lock files in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        WorkerIdProvider {
+            dir: dir.into(),
+            max_machine_id: MAX_MACHINE,
+        }
+    }
+
+    /// Lease ids no larger than `max`
+    pub fn max_machine_id(mut self, max: u64) -> Self {
+        self.max_machine_id = max;
+        self
+    }
+
+    /// The directory holding the lock files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Lease the lowest free machine id
+    ///
+    /// The previous holder may have issued IDs in the current millisecond.
+    /// A generator built with the id must start after it, as
+    /// [`build`](WorkerIdProvider::build) does.
+    pub fn acquire(&self) -> Result<WorkerLease, LeaseError> {
+        self.acquire_up_to(self.max_machine_id)
+    }
+
+    /// Lease a machine id and build a generator with it
+    ///
+    /// The id never exceeds what the builder's layout can hold. The lease is
+    /// held for as long as the returned generator lives.
+    ///
+    /// Holders on one host share its clock, so the previous holder issued
+    /// nothing later than the millisecond the lease was taken in. The
+    /// generator starts after that millisecond.
+    pub fn build<C: Clock>(
+        &self,
+        builder: SnowflakeBuilder<C>,
+    ) -> Result<LeasedSnowflake<C>, LeaseError> {
+        let max = self.max_machine_id.min(builder.layout.max_machine());
+        let lease = self.acquire_up_to(max)?;
+        let generator = builder.machine_id(lease.machine_id()).build()?;
+        generator.advance_past(generator.clock().now_millis())?;
+        Ok(LeasedSnowflake { generator, lease })
+    }
+
+    fn acquire_up_to(&self, max: u64) -> Result<WorkerLease, LeaseError> {
+        fs::create_dir_all(&self.dir)?;
+        for machine_id in 0..=max {
+            let path = self.dir.join(format!("machine-{}.lock", machine_id));
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Error(err)) => return Err(err.into()),
+            }
+            // Record the holder to help whoever inspects the directory
+            file.set_len(0)?;
+            writeln!(file, "{}", std::process::id())?;
+            return Ok(WorkerLease {
+                machine_id,
+                path,
+                _file: file,
+            });
+        }
+        Err(LeaseError::NoFreeId { max })
+    }
+}
+
+/// An exclusively held machine id, released on drop
+///
+/// The lock file itself is left in place: removing it could let another
+/// process lock a fresh file while this one still holds the old one.
+#[derive(Debug)]
+pub struct WorkerLease {
+    machine_id: u64,
+    path: PathBuf,
+    _file: File,
+}
+
+impl WorkerLease {
+    /// The leased machine id
+    pub fn machine_id(&self) -> u64 {
+        self.machine_id
+    }
+
+    /// The lock file backing the lease
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// A generator that owns the lease on its machine id
+pub struct LeasedSnowflake<C> {
+    generator: Snowflake<C>,
+    lease: WorkerLease,
+}
+
+impl<C> LeasedSnowflake<C> {
+    /// The lease on the generator's machine id
+    pub fn lease(&self) -> &WorkerLease {
+        &self.lease
+    }
+
+    /// Split into the generator and its lease
+    pub fn into_parts(self) -> (Snowflake<C>, WorkerLease) {
+        (self.generator, self.lease)
+    }
+}
+
+impl<C> Deref for LeasedSnowflake<C> {
+    type Target = Snowflake<C>;
+
+    fn deref(&self) -> &Snowflake<C> {
+        &self.generator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::epoch::Epoch;
+    use crate::layout::BitLayout;
+    use crate::testing::temp_path;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_leases_lowest_free_id_and_releases_on_drop() {
+        let provider = WorkerIdProvider::new(temp_path("leases-drop"));
+        let first = provider.acquire().unwrap();
+        let second = provider.acquire().unwrap();
+        let third = provider.acquire().unwrap();
+        assert_eq!(
+            (first.machine_id(), second.machine_id(), third.machine_id()),
+            (0, 1, 2)
+        );
+
+        drop(second);
+        assert_eq!(provider.acquire().unwrap().machine_id(), 1);
+        let pid = fs::read_to_string(first.path()).unwrap();
+        assert_eq!(pid.trim(), std::process::id().to_string());
+    }
+
+    #[test]
+    fn test_reports_exhaustion() {
+        let provider = WorkerIdProvider::new(temp_path("leases-exhausted")).max_machine_id(1);
+        let _leases = [provider.acquire().unwrap(), provider.acquire().unwrap()];
+        assert!(matches!(
+            provider.acquire(),
+            Err(LeaseError::NoFreeId { max: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_build_caps_ids_at_the_layout() {
+        let provider = WorkerIdProvider::new(temp_path("leases-layout"));
+        let builder = Snowflake::builder().layout(BitLayout::new(41, 5, 1, 16).unwrap());
+        let first = provider.build(builder.clone()).unwrap();
+        let second = provider.build(builder.clone()).unwrap();
+        assert_eq!(first.next_id().unwrap().as_u64() >> 16 & 1, 0);
+        assert_eq!(second.lease().machine_id(), 1);
+        assert!(matches!(
+            provider.build(builder),
+            Err(LeaseError::NoFreeId { max: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_next_holder_does_not_repeat_ids_in_the_same_millisecond() {
+        let provider = WorkerIdProvider::new(temp_path("leases-handover"));
+        let clock = Arc::new(ManualClock::new(1000));
+        let builder = Snowflake::builder()
+            .epoch(Epoch::UNIX)
+            .clock(Arc::clone(&clock));
+        let first = provider.build(builder.clone()).unwrap();
+        clock.set(1001);
+        let id = first.next_id().unwrap();
+        drop(first);
+
+        let second = provider.build(builder).unwrap();
+        assert!(matches!(
+            second.try_next_id(),
+            Err(SnowflakeError::WouldBlock { .. })
+        ));
+        clock.set(1002);
+        assert_ne!(second.next_id().unwrap(), id);
+    }
+}
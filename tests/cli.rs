@@ -0,0 +1,125 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn snowflake(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_snowflake"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run snowflake");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "snowflake failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_generate_then_decode_from_stdin() {
+    let ids = stdout(&snowflake(
+        &["generate", "-n", "5", "-d", "3", "-m", "4"],
+        "",
+    ));
+    assert_eq!(ids.lines().count(), 5);
+
+    let csv = stdout(&snowflake(&["decode", "--format=csv"], &ids));
+    let mut rows = csv.lines();
+    assert_eq!(
+        rows.next(),
+        Some("id,timestamp,time,datacenter,machine,sequence")
+    );
+    for (row, id) in rows.zip(ids.lines()) {
+        let fields: Vec<&str> = row.split(',').collect();
+        assert_eq!(fields[0], id);
+        assert!(fields[2].ends_with('Z'));
+        assert_eq!(&fields[3..5], ["3", "4"]);
+    }
+}
+
+#[test]
+fn test_generate_json_quotes_ids() {
+    let json = stdout(&snowflake(&["generate", "-n", "2", "-f", "json"], ""));
+    let ids: Vec<&str> = json.trim()[1..json.trim().len() - 1].split(',').collect();
+    assert_eq!(ids.len(), 2);
+    for id in ids {
+        assert!(id.starts_with('"') && id.ends_with('"'), "{}", id);
+        assert!(id[1..id.len() - 1].parse::<u64>().is_ok());
+    }
+}
+
+#[test]
+fn test_decode_with_custom_layout_and_epoch() {
+    let ids = stdout(&snowflake(
+        &[
+            "generate",
+            "--layout",
+            "41,0,10,12",
+            "--epoch",
+            "2024-01-01T00:00:00Z",
+            "-m",
+            "1000",
+        ],
+        "",
+    ));
+    let json = stdout(&snowflake(
+        &[
+            "decode",
+            ids.trim(),
+            "--layout",
+            "41,0,10,12",
+            "--epoch",
+            "2024-01-01T00:00:00Z",
+            "-f",
+            "json",
+        ],
+        "",
+    ));
+    assert!(json.starts_with(&format!("[{{\"id\":\"{}\",", ids.trim())));
+    assert!(json.contains("\"datacenter\":0,\"machine\":1000,"));
+}
+
+#[test]
+fn test_inspect_reports_implausible_ids() {
+    let id = stdout(&snowflake(&["generate"], ""));
+    let output = snowflake(&["inspect", id.trim(), "9223372036854775808"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let text = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], format!("{}: plausible", id.trim()));
+    assert!(lines[1].contains("sign bit is set"));
+
+    // Largest timestamp of the default layout, in 2080
+    let output = snowflake(&["inspect", "9223372036850581504", "-f", "json"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let json = String::from_utf8(output.stdout).unwrap();
+    assert!(json.contains("\"plausible\":false"));
+    assert!(json.contains("in the future"));
+}
+
+#[test]
+fn test_rejects_bad_arguments() {
+    for args in [
+        &["frobnicate"][..],
+        &["generate", "--datacenter", "32"],
+        &["generate", "--format", "xml"],
+        &["decode", "12a"],
+        &["inspect"],
+    ] {
+        let output = snowflake(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?} should fail", args);
+        assert!(!output.stderr.is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "grpc")]
+
+use std::sync::Arc;
+
+use id_gnrt_rust_impl::server::grpc::{GrpcClient, GrpcServer};
+use id_gnrt_rust_impl::{Snowflake, decode};
+use tonic::Code;
+
+async fn start_server() -> GrpcClient {
+    let generator = Arc::new(Snowflake::new(5, 9));
+    let server = GrpcServer::bind("127.0.0.1:0", generator).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(server.serve());
+    GrpcClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_next_id() {
+    let mut client = start_server().await;
+    let id = client.next_id().await.unwrap();
+    assert_eq!((id.datacenter(), id.machine()), (5, 9));
+    assert!(client.next_id().await.unwrap() > id);
+}
+
+#[tokio::test]
+async fn test_next_ids_streams_increasing_ids() {
+    let mut client = start_server().await;
+    let ids = client.next_ids(5_000).await.unwrap();
+    assert_eq!(ids.len(), 5_000);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(client.next_ids(0).await.unwrap().is_empty());
+
+    let err = client.next_ids(10_001).await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn test_decode() {
+    let mut client = start_server().await;
+    let id = client.next_id().await.unwrap();
+    let decoded = client.decode(id).await.unwrap();
+    let expected = decode(id);
+    assert_eq!(decoded.id, id.as_u64());
+    assert_eq!(decoded.timestamp, expected.timestamp);
+    assert_eq!(
+        (decoded.datacenter, decoded.machine, decoded.sequence),
+        (5, 9, expected.sequence)
+    );
+    assert!(decoded.time.ends_with('Z'));
+}
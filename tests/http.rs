@@ -0,0 +1,151 @@
+#![cfg(feature = "server")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use id_gnrt_rust_impl::server::Limits;
+use id_gnrt_rust_impl::server::http::HttpServer;
+use id_gnrt_rust_impl::{Snowflake, SnowflakeId};
+
+fn start_server() -> SocketAddr {
+    let generator = Arc::new(Snowflake::new(3, 7));
+    let server = HttpServer::bind("127.0.0.1:0", generator).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+    addr
+}
+
+/// Send one request on `stream` and read back `(status, body)`
+fn request(stream: &mut TcpStream, method: &str, target: &str) -> (u16, String) {
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        method, target
+    )
+    .unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let status = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        if line.trim_end().is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse().unwrap();
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    (status, String::from_utf8(body).unwrap())
+}
+
+fn get(addr: SocketAddr, target: &str) -> (u16, String) {
+    request(&mut TcpStream::connect(addr).unwrap(), "GET", target)
+}
+
+/// Pull the quoted IDs out of a JSON body
+fn ids_in(body: &str) -> Vec<SnowflakeId> {
+    body.split('"')
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+#[test]
+fn test_health() {
+    let addr = start_server();
+    assert_eq!(
+        get(addr, "/health"),
+        (200, "{\"status\":\"ok\"}".to_string())
+    );
+}
+
+#[test]
+fn test_single_and_batch_ids() {
+    let addr = start_server();
+    let mut stream = TcpStream::connect(addr).unwrap();
+
+    // Several requests over one keep-alive connection
+    let (status, body) = request(&mut stream, "GET", "/id");
+    assert_eq!(status, 200);
+    let first = ids_in(&body);
+    assert_eq!(first.len(), 1);
+    assert_eq!((first[0].datacenter(), first[0].machine()), (3, 7));
+
+    let (status, body) = request(&mut stream, "GET", "/ids?count=500");
+    assert_eq!(status, 200);
+    let batch = ids_in(&body);
+    assert_eq!(batch.len(), 500);
+    assert!(first[0] < batch[0]);
+    assert!(batch.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert_eq!(request(&mut stream, "GET", "/ids?count=10001").0, 400);
+    assert_eq!(request(&mut stream, "GET", "/ids?count=many").0, 400);
+}
+
+#[test]
+fn test_decode() {
+    let addr = start_server();
+    let id = ids_in(&get(addr, "/id").1)[0];
+
+    let (status, body) = get(addr, &format!("/decode/{}", id));
+    assert_eq!(status, 200);
+    assert!(body.starts_with(&format!(
+        "{{\"id\":\"{}\",\"timestamp\":{},",
+        id,
+        id.timestamp()
+    )));
+    assert!(body.contains("\"datacenter\":3,\"machine\":7,"));
+
+    let (status, body) = get(addr, "/decode/12a");
+    assert_eq!(status, 400);
+    assert!(body.starts_with("{\"error\":\"invalid id: invalid character 'a'"));
+}
+
+#[test]
+fn test_unknown_routes() {
+    let addr = start_server();
+    assert_eq!(get(addr, "/nope").0, 404);
+    assert_eq!(
+        request(&mut TcpStream::connect(addr).unwrap(), "POST", "/id").0,
+        405
+    );
+}
+
+#[test]
+fn test_idle_and_excess_connections_are_closed() {
+    let generator = Arc::new(Snowflake::new(3, 7));
+    let server = HttpServer::bind("127.0.0.1:0", generator)
+        .unwrap()
+        .limits(Limits {
+            timeout: Duration::from_millis(200),
+            max_connections: 1,
+        });
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+
+    let mut idle = TcpStream::connect(addr).unwrap();
+    // Give the server time to count the idle connection as open
+    thread::sleep(Duration::from_millis(50));
+    let mut excess = TcpStream::connect(addr).unwrap();
+    excess
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert_eq!(excess.read(&mut [0; 1]).unwrap_or(0), 0);
+
+    let started = Instant::now();
+    idle.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(idle.read(&mut [0; 1]).unwrap(), 0);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // The idle connection's slot is free again
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(get(addr, "/health").0, 200);
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "server")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use id_gnrt_rust_impl::server::resp::RespServer;
+use id_gnrt_rust_impl::{Snowflake, SnowflakeId};
+
+fn start_server() -> SocketAddr {
+    let generator = Arc::new(Snowflake::new(5, 9));
+    let server = RespServer::bind("127.0.0.1:0", generator).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+    addr
+}
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn connect(addr: SocketAddr) -> Self {
+        let writer = TcpStream::connect(addr).unwrap();
+        Client {
+            reader: BufReader::new(writer.try_clone().unwrap()),
+            writer,
+        }
+    }
+
+    /// Send `args` as a RESP array of bulk strings
+    fn send(&mut self, args: &[&str]) {
+        let mut frame = format!("*{}\r\n", args.len());
+        for arg in args {
+            frame.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        self.writer.write_all(frame.as_bytes()).unwrap();
+    }
+
+    fn line(&mut self) -> String {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        assert!(line.ends_with("\r\n"), "unterminated line {:?}", line);
+        line.truncate(line.len() - 2);
+        line
+    }
+
+    /// Read one reply frame back as raw text, nested frames included
+    fn reply(&mut self) -> String {
+        let line = self.line();
+        match line.as_bytes()[0] {
+            b'$' => {
+                let len: usize = line[1..].parse().unwrap();
+                let mut data = vec![0; len + 2];
+                self.reader.read_exact(&mut data).unwrap();
+                format!("{}\r\n{}", line, String::from_utf8_lossy(&data[..len]))
+            }
+            b'*' => {
+                let count: usize = line[1..].parse().unwrap();
+                let mut frame = line;
+                for _ in 0..count {
+                    frame.push_str("\r\n");
+                    frame.push_str(&self.reply());
+                }
+                frame
+            }
+            _ => line,
+        }
+    }
+
+    fn call(&mut self, args: &[&str]) -> String {
+        self.send(args);
+        self.reply()
+    }
+}
+
+#[test]
+fn test_nextid() {
+    let mut client = Client::connect(start_server());
+    let reply = client.call(&["NEXTID"]);
+    let id: SnowflakeId = reply.strip_prefix(':').unwrap().parse().unwrap();
+    assert_eq!((id.datacenter(), id.machine()), (5, 9));
+
+    let next: SnowflakeId = client.call(&["nextid"])[1..].parse().unwrap();
+    assert!(next > id);
+}
+
+#[test]
+fn test_nextids() {
+    let mut client = Client::connect(start_server());
+    let reply = client.call(&["NEXTIDS", "100"]);
+    let mut lines = reply.split("\r\n");
+    assert_eq!(lines.next(), Some("*100"));
+    let ids: Vec<u64> = lines.map(|line| line[1..].parse().unwrap()).collect();
+    assert_eq!(ids.len(), 100);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert_eq!(client.call(&["NEXTIDS", "0"]), "*0");
+    assert!(client.call(&["NEXTIDS", "-1"]).starts_with("-ERR count"));
+    assert!(client.call(&["NEXTIDS"]).starts_with("-ERR wrong number"));
+}
+
+#[test]
+fn test_decode() {
+    let mut client = Client::connect(start_server());
+    let id: SnowflakeId = client.call(&["NEXTID"])[1..].parse().unwrap();
+    let reply = client.call(&["DECODE", &id.to_string()]);
+    let expected = format!(
+        "*10\r\n$9\r\ntimestamp\r\n:{}\r\n$4\r\ntime\r\n",
+        id.timestamp()
+    );
+    assert!(reply.starts_with(&expected), "{:?}", reply);
+    assert!(reply.ends_with(&format!(
+        "$10\r\ndatacenter\r\n:5\r\n$7\r\nmachine\r\n:9\r\n$8\r\nsequence\r\n:{}",
+        id.sequence()
+    )));
+
+    assert!(client.call(&["DECODE", "x"]).starts_with("-ERR invalid id"));
+}
+
+#[test]
+fn test_ping_inline_pipelining_and_quit() {
+    let mut client = Client::connect(start_server());
+    client
+        .writer
+        .write_all(b"PING\r\n*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n*1\r\n$5\r\nHELLO\r\n")
+        .unwrap();
+    assert_eq!(client.reply(), "+PONG");
+    assert_eq!(client.reply(), "$2\r\nhi");
+    assert_eq!(client.reply(), "-ERR unknown command 'HELLO'");
+
+    assert_eq!(client.call(&["QUIT"]), "+OK");
+    let mut rest = Vec::new();
+    client.reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_protocol_error_closes_connection() {
+    let mut client = Client::connect(start_server());
+    client.writer.write_all(b"*1\r\n:1\r\n").unwrap();
+    assert!(client.reply().starts_with("-ERR Protocol error"));
+    let mut rest = Vec::new();
+    client.reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
@@ -0,0 +1,107 @@
+#![cfg(all(unix, feature = "server"))]
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use id_gnrt_rust_impl::Snowflake;
+use id_gnrt_rust_impl::server::Limits;
+use id_gnrt_rust_impl::server::uds::{ERROR_MARKER, UdsClient, UdsServer};
+
+/// A socket path unique to this test process and `name`
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("snowflake-{}-{}.sock", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn start_server(name: &str) -> PathBuf {
+    let path = socket_path(name);
+    let server = UdsServer::bind(&path, Arc::new(Snowflake::new(5, 9))).unwrap();
+    thread::spawn(move || server.serve());
+    path
+}
+
+#[test]
+fn test_client_next_id_and_next_ids() {
+    let mut client = UdsClient::connect(start_server("client")).unwrap();
+    let id = client.next_id().unwrap();
+    assert_eq!((id.datacenter(), id.machine()), (5, 9));
+
+    let ids = client.next_ids(5_000).unwrap();
+    assert_eq!(ids.len(), 5_000);
+    assert!(ids[0] > id);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(client.next_ids(0).unwrap().is_empty());
+}
+
+#[test]
+fn test_raw_frames() {
+    let mut stream = UnixStream::connect(start_server("raw")).unwrap();
+    stream.write_all(&3u32.to_be_bytes()).unwrap();
+    let mut response = [0; 4 + 3 * 8];
+    stream.read_exact(&mut response).unwrap();
+    assert_eq!(response[..4], 3u32.to_be_bytes());
+    let ids: Vec<u64> = response[4..]
+        .chunks(8)
+        .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert!(ids[0] < ids[1] && ids[1] < ids[2]);
+
+    stream.write_all(&10_001u32.to_be_bytes()).unwrap();
+    let mut header = [0; 8];
+    stream.read_exact(&mut header).unwrap();
+    assert_eq!(header[..4], ERROR_MARKER.to_be_bytes());
+    let len = u32::from_be_bytes(header[4..].try_into().unwrap()) as usize;
+    let mut message = vec![0; len];
+    stream.read_exact(&mut message).unwrap();
+    assert!(
+        String::from_utf8(message)
+            .unwrap()
+            .starts_with("count must be")
+    );
+}
+
+#[test]
+fn test_client_reports_server_errors() {
+    let mut client = UdsClient::connect(start_server("errors")).unwrap();
+    let err = client.next_ids(10_001).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert!(err.to_string().starts_with("count must be"));
+    // The connection is still usable afterwards
+    client.next_id().unwrap();
+}
+
+#[test]
+fn test_bind_replaces_stale_socket_only() {
+    let path = socket_path("stale");
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+    let server = UdsServer::bind(&path, Arc::new(Snowflake::new(0, 0))).unwrap();
+
+    let err = UdsServer::bind(&path, Arc::new(Snowflake::new(0, 1)))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+    drop(server);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_idle_connection_is_closed() {
+    let path = socket_path("idle");
+    let server = UdsServer::bind(&path, Arc::new(Snowflake::new(0, 0)))
+        .unwrap()
+        .limits(Limits {
+            timeout: Duration::from_millis(100),
+            ..Limits::default()
+        });
+    thread::spawn(move || server.serve());
+
+    let mut idle = UnixStream::connect(&path).unwrap();
+    idle.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(idle.read(&mut [0; 1]).unwrap(), 0);
+}